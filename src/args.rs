@@ -0,0 +1,846 @@
+use crate::color::{ColorChoice, Colors};
+use crate::ignore::Globs;
+use crate::{CaseMode, Config, MmapChoice, OutputMode, STDIN_PATH, SortBy};
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::IsTerminal;
+use std::os::fd::AsFd;
+use std::os::unix::fs::FileTypeExt;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: rgrep [OPTIONS] <PATTERN> [PATH ...]
+       rgrep [OPTIONS] -e <PATTERN> ... [PATH ...]
+       rgrep [OPTIONS] -f <PATTERNFILE> ... [PATH ...]";
+
+const DEFAULT_PATH: &str = ".";
+
+pub enum Action {
+    Search(Box<Config>),
+    Help,
+    Version,
+}
+
+struct OptionSpec {
+    short: Option<char>,
+    long: &'static str,
+    value_name: Option<&'static str>,
+    help: &'static str,
+}
+
+const OPTIONS: &[OptionSpec] = &[
+    OptionSpec {
+        short: Some('e'),
+        long: "regexp",
+        value_name: Some("PATTERN"),
+        help: "Use PATTERN for matching, even if it starts with '-'; can be repeated",
+    },
+    OptionSpec {
+        short: Some('f'),
+        long: "file",
+        value_name: Some("PATTERNFILE"),
+        help: "Read patterns from PATTERNFILE, one per line; can be repeated",
+    },
+    OptionSpec {
+        short: None,
+        long: "regex",
+        value_name: None,
+        help: "Treat the pattern as a regular expression",
+    },
+    OptionSpec {
+        short: Some('i'),
+        long: "ignore-case",
+        value_name: None,
+        help: "Match case-insensitively",
+    },
+    OptionSpec {
+        short: Some('S'),
+        long: "smart-case",
+        value_name: None,
+        help: "Match case-insensitively unless the pattern has an uppercase letter",
+    },
+    OptionSpec {
+        short: Some('v'),
+        long: "invert-match",
+        value_name: None,
+        help: "Select lines that don't match",
+    },
+    OptionSpec {
+        short: Some('w'),
+        long: "word-regexp",
+        value_name: None,
+        help: "Only match whole words",
+    },
+    OptionSpec {
+        short: Some('x'),
+        long: "line-regexp",
+        value_name: None,
+        help: "Only match whole lines",
+    },
+    OptionSpec {
+        short: Some('o'),
+        long: "only-matching",
+        value_name: None,
+        help: "Print each match on its own line instead of the whole line",
+    },
+    OptionSpec {
+        short: None,
+        long: "column",
+        value_name: None,
+        help: "Print the 1-based column of the first match on each line",
+    },
+    OptionSpec {
+        short: None,
+        long: "color",
+        value_name: Some("WHEN"),
+        help: "Highlight output: auto (when stdout is a terminal), always or never",
+    },
+    OptionSpec {
+        short: None,
+        long: "colors",
+        value_name: Some("SPEC"),
+        help: "Set a color, e.g. match:fg:red or path:none; can be repeated",
+    },
+    OptionSpec {
+        short: Some('c'),
+        long: "count",
+        value_name: None,
+        help: "Print the number of matching lines per file",
+    },
+    OptionSpec {
+        short: None,
+        long: "count-matches",
+        value_name: None,
+        help: "Print the number of matches per file",
+    },
+    OptionSpec {
+        short: Some('l'),
+        long: "files-with-matches",
+        value_name: None,
+        help: "Print only the paths of files with at least one match",
+    },
+    OptionSpec {
+        short: None,
+        long: "files-without-match",
+        value_name: None,
+        help: "Print only the paths of files without any match",
+    },
+    OptionSpec {
+        short: Some('A'),
+        long: "after-context",
+        value_name: Some("NUM"),
+        help: "Print NUM lines of context after each match",
+    },
+    OptionSpec {
+        short: Some('B'),
+        long: "before-context",
+        value_name: Some("NUM"),
+        help: "Print NUM lines of context before each match",
+    },
+    OptionSpec {
+        short: Some('C'),
+        long: "context",
+        value_name: Some("NUM"),
+        help: "Print NUM lines of context before and after each match",
+    },
+    OptionSpec {
+        short: None,
+        long: "no-ignore",
+        value_name: None,
+        help: "Don't respect .gitignore, .ignore and .git/info/exclude files",
+    },
+    OptionSpec {
+        short: Some('g'),
+        long: "glob",
+        value_name: Some("GLOB"),
+        help: "Search only files matching GLOB, or exclude them with !GLOB; can be repeated",
+    },
+    OptionSpec {
+        short: None,
+        long: "iglob",
+        value_name: Some("GLOB"),
+        help: "Like --glob, ignoring case",
+    },
+    OptionSpec {
+        short: Some('L'),
+        long: "follow",
+        value_name: None,
+        help: "Follow symbolic links to files and directories",
+    },
+    OptionSpec {
+        short: None,
+        long: "hidden",
+        value_name: None,
+        help: "Search hidden files and directories, except .git",
+    },
+    OptionSpec {
+        short: Some('u'),
+        long: "unrestricted",
+        value_name: None,
+        help: "Once: --no-ignore; twice: also --hidden and .git; three times: also binary files",
+    },
+    OptionSpec {
+        short: None,
+        long: "json",
+        value_name: None,
+        help: "Print results as JSON Lines, one event per line",
+    },
+    OptionSpec {
+        short: None,
+        long: "heading",
+        value_name: None,
+        help: "Print the path above each file's matches (default on a terminal)",
+    },
+    OptionSpec {
+        short: None,
+        long: "no-heading",
+        value_name: None,
+        help: "Print the path on every matching line",
+    },
+    OptionSpec {
+        short: None,
+        long: "sort",
+        value_name: Some("SORTBY"),
+        help: "Print files in order of path, modified or created; none is the fastest",
+    },
+    OptionSpec {
+        short: None,
+        long: "mmap",
+        value_name: None,
+        help: "Search files through memory maps when possible (default for large files named as paths)",
+    },
+    OptionSpec {
+        short: None,
+        long: "no-mmap",
+        value_name: None,
+        help: "Never search files through memory maps",
+    },
+    OptionSpec {
+        short: Some('s'),
+        long: "no-messages",
+        value_name: None,
+        help: "Suppress error messages about files that can't be read",
+    },
+    OptionSpec {
+        short: None,
+        long: "error-summary",
+        value_name: None,
+        help: "Print the number of errors per category at the end",
+    },
+    OptionSpec {
+        short: None,
+        long: "label",
+        value_name: Some("LABEL"),
+        help: "Show LABEL instead of '(standard input)' for matches read from stdin",
+    },
+    OptionSpec {
+        short: Some('h'),
+        long: "help",
+        value_name: None,
+        help: "Print help and exit",
+    },
+    OptionSpec {
+        short: Some('V'),
+        long: "version",
+        value_name: None,
+        help: "Print version and exit",
+    },
+];
+
+pub fn help() -> String {
+    let mut help = format!("{USAGE}\n\nOptions:\n");
+
+    for option in OPTIONS {
+        let short = match option.short {
+            Some(short) => format!("-{short}, "),
+            None => "    ".to_string(),
+        };
+        let long = match option.value_name {
+            Some(value_name) => format!("--{} <{}>", option.long, value_name),
+            None => format!("--{}", option.long),
+        };
+        help.push_str(&format!("  {short}{long:<28} {}\n", option.help));
+    }
+
+    help
+}
+
+/// Options collected while parsing, before they are validated into a `Config`.
+#[derive(Default)]
+struct ParsedArgs {
+    regex_mode: bool,
+    case_mode: CaseMode,
+    invert_match: bool,
+    word_regexp: bool,
+    line_regexp: bool,
+    output_mode: OutputMode,
+    only_matching: bool,
+    column: bool,
+    color: ColorChoice,
+    colors: Colors,
+    no_ignore: bool,
+    hidden: bool,
+    follow: bool,
+    /// Globs in the order given, with whether each ignores case
+    globs: Vec<(String, bool)>,
+    /// How many times -u was given
+    unrestricted: usize,
+    sort: SortBy,
+    heading: Option<bool>,
+    json: bool,
+    mmap: MmapChoice,
+    no_messages: bool,
+    error_summary: bool,
+    context: Option<usize>,
+    before_context: Option<usize>,
+    after_context: Option<usize>,
+    label: Option<String>,
+    patterns: Vec<String>,
+    has_pattern_file: bool,
+    positionals: Vec<String>,
+    help: bool,
+    version: bool,
+}
+
+impl ParsedArgs {
+    fn apply(&mut self, option: &OptionSpec, value: Option<String>) -> Result<(), String> {
+        let value = value.unwrap_or_default();
+
+        match option.long {
+            "regexp" => self.patterns.push(value),
+            "file" => {
+                let contents = fs::read_to_string(&value)
+                    .map_err(|e| format!("error reading pattern file {value}: {e}"))?;
+                self.patterns.extend(contents.lines().map(str::to_string));
+                self.has_pattern_file = true;
+            }
+            "regex" => self.regex_mode = true,
+            "ignore-case" => self.case_mode = CaseMode::Insensitive,
+            "smart-case" => self.case_mode = CaseMode::Smart,
+            "invert-match" => self.invert_match = true,
+            "word-regexp" => self.word_regexp = true,
+            "line-regexp" => self.line_regexp = true,
+            "only-matching" => self.only_matching = true,
+            "column" => self.column = true,
+            "color" => self.color = ColorChoice::parse(&value)?,
+            "colors" => self.colors.apply_spec(&value)?,
+            "count" => self.output_mode = OutputMode::Count,
+            "count-matches" => self.output_mode = OutputMode::CountMatches,
+            "files-with-matches" => self.output_mode = OutputMode::FilesWithMatches,
+            "files-without-match" => self.output_mode = OutputMode::FilesWithoutMatch,
+            "after-context" => self.after_context = Some(parse_number(option, &value)?),
+            "before-context" => self.before_context = Some(parse_number(option, &value)?),
+            "context" => self.context = Some(parse_number(option, &value)?),
+            "no-ignore" => self.no_ignore = true,
+            "hidden" => self.hidden = true,
+            "follow" => self.follow = true,
+            "glob" => self.globs.push((value, false)),
+            "iglob" => self.globs.push((value, true)),
+            "unrestricted" => self.unrestricted += 1,
+            "sort" => self.sort = SortBy::parse(&value)?,
+            "json" => self.json = true,
+            "heading" => self.heading = Some(true),
+            "no-heading" => self.heading = Some(false),
+            "mmap" => self.mmap = MmapChoice::Always,
+            "no-mmap" => self.mmap = MmapChoice::Never,
+            "no-messages" => self.no_messages = true,
+            "error-summary" => self.error_summary = true,
+            "label" => self.label = Some(value),
+            "help" => self.help = true,
+            "version" => self.version = true,
+            _ => unreachable!("option --{} is not handled", option.long),
+        }
+
+        Ok(())
+    }
+
+    fn into_config(mut self, stdin_readable: bool) -> Result<Config, String> {
+        if self.json && self.output_mode != OutputMode::Lines {
+            return Err("--json can't be combined with counts or file lists".to_string());
+        }
+
+        let globs = Globs::new(
+            self.globs
+                .iter()
+                .map(|(glob, case_insensitive)| (glob.as_str(), *case_insensitive)),
+        )?;
+
+        let mut positionals = self.positionals.into_iter();
+
+        // Without -e or -f, the first positional argument is the pattern
+        if self.patterns.is_empty() && !self.has_pattern_file {
+            self.patterns
+                .push(positionals.next().ok_or("missing <PATTERN> argument")?);
+        }
+
+        let mut paths: Vec<PathBuf> = positionals.map(PathBuf::from).collect();
+        if paths.is_empty() {
+            // Piped or redirected input is searched instead of the current directory
+            let default_path = if stdin_readable {
+                STDIN_PATH
+            } else {
+                DEFAULT_PATH
+            };
+            paths.push(PathBuf::from(default_path));
+        }
+
+        Ok(Config {
+            regex_mode: self.regex_mode,
+            case_mode: self.case_mode,
+            invert_match: self.invert_match,
+            word_regexp: self.word_regexp,
+            line_regexp: self.line_regexp,
+            patterns: self.patterns,
+            paths,
+            label: self.label,
+            no_ignore: self.no_ignore || self.unrestricted >= 1,
+            hidden: self.hidden || self.unrestricted >= 2,
+            binary: self.unrestricted >= 3,
+            follow: self.follow,
+            globs,
+            // -A and -B take precedence over -C regardless of their order
+            before_context: self.before_context.or(self.context).unwrap_or(0),
+            after_context: self.after_context.or(self.context).unwrap_or(0),
+            output_mode: self.output_mode,
+            only_matching: self.only_matching,
+            column: self.column,
+            color: self.color,
+            colors: self.colors,
+            sort: self.sort,
+            heading: self.heading,
+            json: self.json,
+            mmap: self.mmap,
+            no_messages: self.no_messages,
+            error_summary: self.error_summary,
+        })
+    }
+}
+
+fn parse_number(option: &OptionSpec, value: &str) -> Result<usize, String> {
+    value.parse::<usize>().map_err(|_| {
+        format!(
+            "invalid value '{value}' for --{}: expected a number",
+            option.long
+        )
+    })
+}
+
+fn find_long(name: &str) -> Result<&'static OptionSpec, String> {
+    OPTIONS
+        .iter()
+        .find(|option| option.long == name)
+        .ok_or_else(|| format!("unrecognized option '--{name}'"))
+}
+
+fn find_short(short: char) -> Result<&'static OptionSpec, String> {
+    OPTIONS
+        .iter()
+        .find(|option| option.short == Some(short))
+        .ok_or_else(|| format!("unrecognized option '-{short}'"))
+}
+
+/// Returns whether stdin is a pipe, file or socket that can be searched when no path is given.
+pub fn stdin_is_readable() -> bool {
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        return false;
+    }
+
+    // Other kinds of stdin, like /dev/null or a closed descriptor, aren't worth searching
+    let file_type = stdin
+        .as_fd()
+        .try_clone_to_owned()
+        .map(File::from)
+        .and_then(|file| file.metadata())
+        .map(|metadata| metadata.file_type());
+
+    match file_type {
+        Ok(file_type) => file_type.is_file() || file_type.is_fifo() || file_type.is_socket(),
+        Err(_) => false,
+    }
+}
+
+/// Parses the command-line arguments, excluding the executable path.
+///
+/// `stdin_readable` decides whether stdin or the current directory is searched when no path
+/// is given.
+pub fn parse_args<I>(args: I, stdin_readable: bool) -> Result<Action, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut parsed = ParsedArgs::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            // Everything after the terminator is positional
+            parsed.positionals.extend(args.by_ref());
+        } else if let Some(long) = arg.strip_prefix("--") {
+            let (name, inline_value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+            let option = find_long(name)?;
+
+            let value =
+                match (option.value_name, inline_value) {
+                    (Some(_), Some(value)) => Some(value),
+                    (Some(value_name), None) => Some(args.next().ok_or_else(|| {
+                        format!("option '--{name}' requires a <{value_name}> value")
+                    })?),
+                    (None, Some(_)) => return Err(format!("option '--{name}' takes no value")),
+                    (None, None) => None,
+                };
+            parsed.apply(option, value)?;
+        } else if arg.len() > 1 && arg.starts_with('-') {
+            // Short flags can be clustered, e.g. "-iS" or "-C2"
+            for (index, short) in arg[1..].char_indices() {
+                let option = find_short(short)?;
+
+                if let Some(value_name) = option.value_name {
+                    let rest = &arg[1 + index + short.len_utf8()..];
+                    let value = if rest.is_empty() {
+                        args.next().ok_or_else(|| {
+                            format!("option '-{short}' requires a <{value_name}> value")
+                        })?
+                    } else {
+                        rest.to_string()
+                    };
+                    parsed.apply(option, Some(value))?;
+                    break;
+                }
+                parsed.apply(option, None)?;
+            }
+        } else {
+            parsed.positionals.push(arg);
+        }
+    }
+
+    if parsed.help {
+        Ok(Action::Help)
+    } else if parsed.version {
+        Ok(Action::Version)
+    } else {
+        parsed
+            .into_config(stdin_readable)
+            .map(|config| Action::Search(Box::new(config)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn parse(args: &[&str]) -> Result<Action, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()), false)
+    }
+
+    fn parse_config(args: &[&str]) -> Config {
+        match parse(args) {
+            Ok(Action::Search(config)) => *config,
+            Ok(_) => panic!("expected a search action"),
+            Err(e) => panic!("unexpected error: {e}"),
+        }
+    }
+
+    #[test]
+    fn parse_args_pattern_and_path() {
+        let config = parse_config(&["Hello", "src"]);
+
+        assert_eq!(config.patterns, vec!["Hello"]);
+        assert_eq!(config.paths, vec![PathBuf::from("src")]);
+        assert!(!config.regex_mode);
+        assert_eq!(config.case_mode, CaseMode::Sensitive);
+    }
+
+    #[test]
+    fn parse_args_flags_anywhere() {
+        let config = parse_config(&["Hello", "--regex", "src", "-i"]);
+
+        assert!(config.regex_mode);
+        assert_eq!(config.case_mode, CaseMode::Insensitive);
+        assert_eq!(config.patterns, vec!["Hello"]);
+    }
+
+    #[test]
+    fn parse_args_clustered_short_flags() {
+        let config = parse_config(&["-iC2", "Hello", "src"]);
+
+        assert_eq!(config.case_mode, CaseMode::Insensitive);
+        assert_eq!(config.before_context, 2);
+        assert_eq!(config.after_context, 2);
+    }
+
+    #[test]
+    fn parse_args_globs_keep_their_order_and_case() {
+        let config = parse_config(&["-g", "*.rs", "--iglob", "!*.MD", "--glob=!gen/**", "a"]);
+
+        assert!(!config.globs.is_excluded(Path::new("a.rs"), false));
+        assert!(config.globs.is_excluded(Path::new("a.md"), false));
+        assert!(config.globs.is_excluded(Path::new("gen"), true));
+        assert!(config.globs.is_excluded(Path::new("a.c"), false));
+        assert_eq!(
+            parse(&["-g", "[z-a]", "a"]).err().as_deref(),
+            Some("invalid glob '[z-a]'")
+        );
+    }
+
+    #[test]
+    fn parse_args_follow_takes_short_l() {
+        assert!(!parse_config(&["a"]).follow);
+        assert!(parse_config(&["-L", "a"]).follow);
+        assert_eq!(parse_config(&["-L", "a"]).output_mode, OutputMode::Lines);
+    }
+
+    #[test]
+    fn parse_args_unrestricted_ladder() {
+        let config = parse_config(&["a"]);
+        assert!(!config.no_ignore && !config.hidden && !config.binary);
+
+        let config = parse_config(&["--hidden", "a"]);
+        assert!(!config.no_ignore && config.hidden && !config.binary);
+
+        let config = parse_config(&["-u", "a"]);
+        assert!(config.no_ignore && !config.hidden && !config.binary);
+
+        let config = parse_config(&["-uu", "a"]);
+        assert!(config.no_ignore && config.hidden && !config.binary);
+
+        let config = parse_config(&["-u", "--unrestricted", "-u", "a"]);
+        assert!(config.no_ignore && config.hidden && config.binary);
+    }
+
+    #[test]
+    fn parse_args_match_mode_flags() {
+        let config = parse_config(&["-vwx", "Hello"]);
+
+        assert!(config.invert_match);
+        assert!(config.word_regexp);
+        assert!(config.line_regexp);
+    }
+
+    #[test]
+    fn parse_args_only_matching_and_column() {
+        let config = parse_config(&["-o", "--column", "Hello"]);
+
+        assert!(config.only_matching);
+        assert!(config.column);
+    }
+
+    #[test]
+    fn parse_args_color_options() {
+        let config = parse_config(&["--color=always", "--colors", "match:fg:blue", "a"]);
+
+        assert_eq!(config.color, ColorChoice::Always);
+        assert_eq!(config.colors.matched.fg, Some(crate::color::Color::Blue));
+
+        assert_eq!(parse_config(&["a"]).color, ColorChoice::Auto);
+        assert!(parse(&["--color=rainbow", "a"]).is_err());
+        assert!(parse(&["--colors=match:fg:nope", "a"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_last_output_mode_wins() {
+        assert_eq!(parse_config(&["-c", "a"]).output_mode, OutputMode::Count);
+        assert_eq!(
+            parse_config(&["-c", "--count-matches", "a"]).output_mode,
+            OutputMode::CountMatches
+        );
+        assert_eq!(
+            parse_config(&["-cl", "a"]).output_mode,
+            OutputMode::FilesWithMatches
+        );
+        assert_eq!(
+            parse_config(&["-l", "--files-without-match", "a"]).output_mode,
+            OutputMode::FilesWithoutMatch
+        );
+    }
+
+    #[test]
+    fn parse_args_last_case_flag_wins() {
+        let config = parse_config(&["-iS", "Hello", "src"]);
+
+        assert_eq!(config.case_mode, CaseMode::Smart);
+    }
+
+    #[test]
+    fn parse_args_long_option_with_equals_value() {
+        let config = parse_config(&["--after-context=3", "--before-context", "1", "a", "b"]);
+
+        assert_eq!(config.after_context, 3);
+        assert_eq!(config.before_context, 1);
+    }
+
+    #[test]
+    fn parse_args_explicit_context_overrides_context() {
+        let config = parse_config(&["-A", "4", "-C", "1", "a", "b"]);
+
+        assert_eq!(config.after_context, 4);
+        assert_eq!(config.before_context, 1);
+    }
+
+    #[test]
+    fn parse_args_regexp_allows_dash_patterns() {
+        let config = parse_config(&["-e", "-foo", "src"]);
+
+        assert_eq!(config.patterns, vec!["-foo"]);
+        assert_eq!(config.paths, vec![PathBuf::from("src")]);
+
+        let config = parse_config(&["-e-bar", "src"]);
+
+        assert_eq!(config.patterns, vec!["-bar"]);
+    }
+
+    #[test]
+    fn parse_args_repeated_regexp_collects_patterns() {
+        let config = parse_config(&["-e", "foo", "-ebar", "--regexp=baz", "src"]);
+
+        assert_eq!(config.patterns, vec!["foo", "bar", "baz"]);
+        assert_eq!(config.paths, vec![PathBuf::from("src")]);
+    }
+
+    #[test]
+    fn parse_args_pattern_file_adds_one_pattern_per_line() {
+        let mut pattern_file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut pattern_file, b"old_api\nlegacy_call\n").unwrap();
+        let pattern_path = pattern_file.path().to_str().unwrap();
+
+        let config = parse_config(&["-f", pattern_path, "-e", "extra", "src"]);
+
+        assert_eq!(config.patterns, vec!["old_api", "legacy_call", "extra"]);
+        assert_eq!(config.paths, vec![PathBuf::from("src")]);
+    }
+
+    #[test]
+    fn parse_args_empty_pattern_file_has_no_patterns() {
+        let pattern_file = tempfile::NamedTempFile::new().unwrap();
+        let pattern_path = pattern_file.path().to_str().unwrap();
+
+        let config = parse_config(&["-f", pattern_path, "src"]);
+
+        assert!(config.patterns.is_empty());
+        assert_eq!(config.paths, vec![PathBuf::from("src")]);
+    }
+
+    #[test]
+    fn parse_args_missing_pattern_file_is_an_error() {
+        assert!(parse(&["-f", "does/not/exist", "src"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_terminator_makes_rest_positional() {
+        let config = parse_config(&["-i", "--", "--regex", "-src"]);
+
+        assert!(!config.regex_mode);
+        assert_eq!(config.patterns, vec!["--regex"]);
+        assert_eq!(config.paths, vec![PathBuf::from("-src")]);
+    }
+
+    #[test]
+    fn parse_args_help_and_version() {
+        assert!(matches!(parse(&["--help"]), Ok(Action::Help)));
+        assert!(matches!(parse(&["-h", "pattern"]), Ok(Action::Help)));
+        assert!(matches!(parse(&["-V"]), Ok(Action::Version)));
+    }
+
+    #[test]
+    fn parse_args_unknown_options_are_errors() {
+        assert!(parse(&["--nope", "a", "b"]).is_err());
+        assert!(parse(&["-iz", "a", "b"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_invalid_values_are_errors() {
+        assert!(parse(&["-A", "x", "a", "b"]).is_err());
+        assert!(parse(&["a", "b", "-C"]).is_err());
+        assert!(parse(&["--regex=yes", "a", "b"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_sort() {
+        assert_eq!(parse_config(&["a"]).sort, SortBy::None);
+        assert_eq!(parse_config(&["--sort", "path", "a"]).sort, SortBy::Path);
+        assert_eq!(
+            parse_config(&["--sort=modified", "a"]).sort,
+            SortBy::Modified
+        );
+        assert!(parse(&["--sort=size", "a"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_heading_last_one_wins() {
+        assert_eq!(parse_config(&["a"]).heading, None);
+        assert_eq!(parse_config(&["--heading", "a"]).heading, Some(true));
+        assert_eq!(
+            parse_config(&["--heading", "--no-heading", "a"]).heading,
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn parse_args_mmap_last_one_wins() {
+        assert_eq!(parse_config(&["a"]).mmap, MmapChoice::Auto);
+        assert_eq!(parse_config(&["--mmap", "a"]).mmap, MmapChoice::Always);
+        assert_eq!(
+            parse_config(&["--mmap", "--no-mmap", "a"]).mmap,
+            MmapChoice::Never
+        );
+    }
+
+    #[test]
+    fn parse_args_json_only_prints_lines() {
+        assert!(parse_config(&["--json", "a"]).json);
+        assert!(parse(&["--json", "-c", "a"]).is_err());
+        assert!(parse(&["-l", "--json", "a"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_multiple_paths() {
+        let config = parse_config(&["Hello", "src", "tests", "build.rs"]);
+
+        assert_eq!(
+            config.paths,
+            vec![
+                PathBuf::from("src"),
+                PathBuf::from("tests"),
+                PathBuf::from("build.rs")
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_args_defaults_to_current_directory() {
+        let config = parse_config(&["Hello"]);
+
+        assert_eq!(config.paths, vec![PathBuf::from(".")]);
+
+        let config = parse_config(&["-e", "Hello"]);
+
+        assert_eq!(config.paths, vec![PathBuf::from(".")]);
+    }
+
+    #[test]
+    fn parse_args_defaults_to_stdin_when_readable() {
+        let args = ["Hello".to_string()];
+
+        match parse_args(args, true) {
+            Ok(Action::Search(config)) => assert_eq!(config.paths, vec![PathBuf::from("-")]),
+            _ => panic!("expected a search action"),
+        }
+    }
+
+    #[test]
+    fn parse_args_label() {
+        let config = parse_config(&["--label=input.log", "Hello", "-"]);
+
+        assert_eq!(config.label.as_deref(), Some("input.log"));
+        assert_eq!(config.paths, vec![PathBuf::from("-")]);
+    }
+
+    #[test]
+    fn parse_args_missing_pattern_is_an_error() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["-i"]).is_err());
+    }
+}
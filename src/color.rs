@@ -0,0 +1,274 @@
+use std::env;
+use std::io::IsTerminal;
+
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color only when stdout is a terminal and `NO_COLOR` isn't set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn parse(value: &str) -> Result<ColorChoice, String> {
+        match value {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!(
+                "invalid color choice '{value}': expected auto, always or never"
+            )),
+        }
+    }
+
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+                !no_color && std::io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    /// One of the 256 ANSI palette colors
+    Ansi256(u8),
+}
+
+impl Color {
+    fn parse(value: &str) -> Result<Color, String> {
+        let color = match value {
+            "black" => Color::Black,
+            "red" => Color::Red,
+            "green" => Color::Green,
+            "yellow" => Color::Yellow,
+            "blue" => Color::Blue,
+            "magenta" => Color::Magenta,
+            "cyan" => Color::Cyan,
+            "white" => Color::White,
+            _ => Color::Ansi256(
+                value
+                    .parse::<u8>()
+                    .map_err(|_| format!("invalid color '{value}'"))?,
+            ),
+        };
+        Ok(color)
+    }
+
+    /// Returns the SGR code for this color, offset by 30 for foreground or 40 for background.
+    fn code(self, base: u8) -> String {
+        let offset = match self {
+            Color::Black => 0,
+            Color::Red => 1,
+            Color::Green => 2,
+            Color::Yellow => 3,
+            Color::Blue => 4,
+            Color::Magenta => 5,
+            Color::Cyan => 6,
+            Color::White => 7,
+            Color::Ansi256(index) => return format!("{};5;{}", base + 8, index),
+        };
+        (base + offset).to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ColorSpec {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub underline: bool,
+}
+
+impl ColorSpec {
+    fn is_plain(&self) -> bool {
+        *self == ColorSpec::default()
+    }
+
+    fn prefix(&self) -> String {
+        let mut codes = Vec::new();
+        if self.bold {
+            codes.push("1".to_string());
+        }
+        if self.underline {
+            codes.push("4".to_string());
+        }
+        if let Some(fg) = self.fg {
+            codes.push(fg.code(30));
+        }
+        if let Some(bg) = self.bg {
+            codes.push(bg.code(40));
+        }
+        format!("\x1b[{}m", codes.join(";"))
+    }
+
+    /// Wraps `text` in the escape sequences for this spec.
+    pub fn paint(&self, text: &str) -> String {
+        if self.is_plain() || text.is_empty() {
+            return text.to_string();
+        }
+        format!("{}{}{}", self.prefix(), text, RESET)
+    }
+}
+
+/// The colors used for each part of the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Colors {
+    pub path: ColorSpec,
+    pub line: ColorSpec,
+    pub column: ColorSpec,
+    pub matched: ColorSpec,
+    pub separator: ColorSpec,
+}
+
+impl Default for Colors {
+    fn default() -> Colors {
+        Colors {
+            path: ColorSpec {
+                fg: Some(Color::Magenta),
+                ..ColorSpec::default()
+            },
+            line: ColorSpec {
+                fg: Some(Color::Green),
+                ..ColorSpec::default()
+            },
+            column: ColorSpec {
+                fg: Some(Color::Green),
+                ..ColorSpec::default()
+            },
+            matched: ColorSpec {
+                fg: Some(Color::Red),
+                bold: true,
+                ..ColorSpec::default()
+            },
+            separator: ColorSpec::default(),
+        }
+    }
+}
+
+impl Colors {
+    /// Applies a `--colors` spec such as `match:fg:red`, `path:style:bold` or `line:none`.
+    pub fn apply_spec(&mut self, spec: &str) -> Result<(), String> {
+        let invalid =
+            || format!("invalid color spec '{spec}': expected {{type}}:{{attribute}}:{{value}}");
+        let mut parts = spec.split(':');
+        let (kind, attribute) = match (parts.next(), parts.next()) {
+            (Some(kind), Some(attribute)) => (kind, attribute),
+            _ => return Err(invalid()),
+        };
+        let value = parts.next();
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        let target = match kind {
+            "path" => &mut self.path,
+            "line" => &mut self.line,
+            "column" => &mut self.column,
+            "match" => &mut self.matched,
+            "separator" => &mut self.separator,
+            _ => return Err(format!("invalid color spec type '{kind}'")),
+        };
+
+        match (attribute, value) {
+            ("none", None) => *target = ColorSpec::default(),
+            ("fg", Some(value)) => target.fg = Some(Color::parse(value)?),
+            ("bg", Some(value)) => target.bg = Some(Color::parse(value)?),
+            ("style", Some("bold")) => target.bold = true,
+            ("style", Some("nobold")) => target.bold = false,
+            ("style", Some("underline")) => target.underline = true,
+            ("style", Some("nounderline")) => target.underline = false,
+            ("style", Some(value)) => return Err(format!("invalid color style '{value}'")),
+            _ => return Err(invalid()),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_choice_parse_accepts_known_values() {
+        assert_eq!(ColorChoice::parse("auto"), Ok(ColorChoice::Auto));
+        assert_eq!(ColorChoice::parse("always"), Ok(ColorChoice::Always));
+        assert_eq!(ColorChoice::parse("never"), Ok(ColorChoice::Never));
+        assert!(ColorChoice::parse("sometimes").is_err());
+    }
+
+    #[test]
+    fn paint_wraps_text_in_escape_sequences() {
+        let spec = ColorSpec {
+            fg: Some(Color::Red),
+            bold: true,
+            ..ColorSpec::default()
+        };
+
+        assert_eq!(spec.paint("hi"), "\x1b[1;31mhi\x1b[0m");
+    }
+
+    #[test]
+    fn paint_plain_spec_or_empty_text_is_unchanged() {
+        assert_eq!(ColorSpec::default().paint("hi"), "hi");
+        assert_eq!(Colors::default().matched.paint(""), "");
+    }
+
+    #[test]
+    fn paint_256_colors_and_background() {
+        let spec = ColorSpec {
+            fg: Some(Color::Ansi256(208)),
+            bg: Some(Color::Blue),
+            ..ColorSpec::default()
+        };
+
+        assert_eq!(spec.paint("x"), "\x1b[38;5;208;44mx\x1b[0m");
+    }
+
+    #[test]
+    fn apply_spec_updates_the_given_type() {
+        let mut colors = Colors::default();
+
+        colors.apply_spec("match:fg:blue").unwrap();
+        colors.apply_spec("match:style:nobold").unwrap();
+        colors.apply_spec("path:none").unwrap();
+        colors.apply_spec("separator:style:underline").unwrap();
+
+        assert_eq!(
+            colors.matched,
+            ColorSpec {
+                fg: Some(Color::Blue),
+                ..ColorSpec::default()
+            }
+        );
+        assert_eq!(colors.path, ColorSpec::default());
+        assert!(colors.separator.underline);
+    }
+
+    #[test]
+    fn apply_spec_rejects_invalid_specs() {
+        let mut colors = Colors::default();
+
+        assert!(colors.apply_spec("match").is_err());
+        assert!(colors.apply_spec("nope:fg:red").is_err());
+        assert!(colors.apply_spec("match:fg:purple").is_err());
+        assert!(colors.apply_spec("match:style:blink").is_err());
+        assert!(colors.apply_spec("match:fg:red:extra").is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+/// The category of an `Error`, to report summaries or handle some errors differently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorKind {
+    PermissionDenied,
+    NotFound,
+    /// Any other I/O error while reading input or writing output
+    Io,
+    InvalidPattern,
+    /// A directory that contains itself, through symbolic links
+    Loop,
+}
+
+impl ErrorKind {
+    pub fn description(self) -> &'static str {
+        match self {
+            ErrorKind::PermissionDenied => "permission denied",
+            ErrorKind::NotFound => "not found",
+            ErrorKind::Io => "I/O error",
+            ErrorKind::InvalidPattern => "invalid pattern",
+            ErrorKind::Loop => "file system loop",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    PermissionDenied {
+        path: PathBuf,
+        source: io::Error,
+    },
+    NotFound {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// Writing the results failed, e.g. because stdout was closed
+    Output(io::Error),
+    InvalidPattern(regex::Error),
+    Loop {
+        path: PathBuf,
+        ancestor: PathBuf,
+    },
+}
+
+impl Error {
+    /// Wraps an I/O error on `path`, categorized by its `io::ErrorKind`.
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Error {
+        let path = path.into();
+        match source.kind() {
+            io::ErrorKind::PermissionDenied => Error::PermissionDenied { path, source },
+            io::ErrorKind::NotFound => Error::NotFound { path, source },
+            _ => Error::Io { path, source },
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::PermissionDenied { .. } => ErrorKind::PermissionDenied,
+            Error::NotFound { .. } => ErrorKind::NotFound,
+            Error::Io { .. } | Error::Output(_) => ErrorKind::Io,
+            Error::InvalidPattern(_) => ErrorKind::InvalidPattern,
+            Error::Loop { .. } => ErrorKind::Loop,
+        }
+    }
+
+    /// Returns the path the error happened on, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::PermissionDenied { path, .. }
+            | Error::NotFound { path, .. }
+            | Error::Io { path, .. }
+            | Error::Loop { path, .. } => Some(path),
+            Error::Output(_) | Error::InvalidPattern(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::PermissionDenied { path, source }
+            | Error::NotFound { path, source }
+            | Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Output(source) => write!(f, "error writing output: {}", source),
+            Error::InvalidPattern(source) => write!(f, "regex not valid: {}", source),
+            Error::Loop { path, ancestor } => write!(
+                f,
+                "{}: file system loop found, it points to ancestor {}",
+                path.display(),
+                ancestor.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::PermissionDenied { source, .. }
+            | Error::NotFound { source, .. }
+            | Error::Io { source, .. }
+            | Error::Output(source) => Some(source),
+            Error::InvalidPattern(source) => Some(source),
+            Error::Loop { .. } => None,
+        }
+    }
+}
+
+/// Collects the errors of a search, printing each one as it happens unless told not to.
+#[derive(Debug, Default)]
+pub struct ErrorReporter {
+    errors: Mutex<Vec<Error>>,
+    no_messages: bool,
+}
+
+impl ErrorReporter {
+    pub fn new(no_messages: bool) -> ErrorReporter {
+        ErrorReporter {
+            errors: Mutex::new(Vec::new()),
+            no_messages,
+        }
+    }
+
+    pub fn report(&self, error: Error) {
+        if !self.no_messages {
+            eprintln!("rgrep: {error}");
+        }
+        self.errors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(error);
+    }
+
+    pub fn into_errors(self) -> Vec<Error> {
+        self.errors
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Formats a one-line summary such as `3 errors: 2 permission denied, 1 not found`.
+pub fn summary(errors: &[Error]) -> String {
+    let mut counts: BTreeMap<ErrorKind, usize> = BTreeMap::new();
+    for error in errors {
+        *counts.entry(error.kind()).or_default() += 1;
+    }
+
+    let noun = if errors.len() == 1 { "error" } else { "errors" };
+    let mut summary = format!("{} {}", errors.len(), noun);
+    let categories: Vec<String> = counts
+        .iter()
+        .map(|(kind, count)| format!("{} {}", count, kind.description()))
+        .collect();
+    if !categories.is_empty() {
+        summary.push_str(": ");
+        summary.push_str(&categories.join(", "));
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_errors_are_categorized_by_kind() {
+        let denied = Error::io("a", io::Error::from(io::ErrorKind::PermissionDenied));
+        let missing = Error::io("b", io::Error::from(io::ErrorKind::NotFound));
+        let other = Error::io("c", io::Error::from(io::ErrorKind::UnexpectedEof));
+
+        assert_eq!(denied.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(missing.kind(), ErrorKind::NotFound);
+        assert_eq!(other.kind(), ErrorKind::Io);
+        assert_eq!(other.path(), Some(Path::new("c")));
+    }
+
+    #[test]
+    fn display_starts_with_the_path() {
+        let error = Error::io("a.txt", io::Error::other("disk on fire"));
+
+        assert_eq!(error.to_string(), "a.txt: disk on fire");
+    }
+
+    #[test]
+    fn reporter_collects_errors() {
+        let reporter = ErrorReporter::new(true);
+        reporter.report(Error::io("a", io::Error::from(io::ErrorKind::NotFound)));
+
+        let errors = reporter.into_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn summary_counts_errors_per_category() {
+        let errors = vec![
+            Error::io("a", io::Error::from(io::ErrorKind::NotFound)),
+            Error::io("b", io::Error::from(io::ErrorKind::PermissionDenied)),
+            Error::io("c", io::Error::from(io::ErrorKind::PermissionDenied)),
+        ];
+
+        assert_eq!(
+            summary(&errors),
+            "3 errors: 2 permission denied, 1 not found"
+        );
+        assert_eq!(summary(&[]), "0 errors");
+    }
+}
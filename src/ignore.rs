@@ -0,0 +1,577 @@
+use regex::{Regex, RegexSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Ignore files read in every directory, from lowest to highest precedence.
+pub const IGNORE_FILE_NAMES: [&str; 2] = [".gitignore", ".ignore"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Match {
+    None,
+    Ignore,
+    Whitelist,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    negated: bool,
+    dir_only: bool,
+}
+
+/// A compiled set of gitignore-style rules, relative to the directory they were read from.
+#[derive(Debug)]
+pub struct Gitignore {
+    root: PathBuf,
+    /// Where `root` is relative to the directory the rules were read from, when they were read
+    /// above it
+    base: PathBuf,
+    rules: Vec<Rule>,
+    set: RegexSet,
+}
+
+impl Gitignore {
+    pub fn from_file(root: &Path, file: &Path) -> io::Result<Gitignore> {
+        let contents = fs::read_to_string(file)?;
+        Ok(Gitignore::parse(root, &contents))
+    }
+
+    pub fn parse(root: &Path, contents: &str) -> Gitignore {
+        let mut rules = Vec::new();
+        let mut regexes = Vec::new();
+
+        for line in contents.lines() {
+            let Some((rule, regex)) = parse_line(line) else {
+                continue;
+            };
+            // A line with an invalid class such as `[z-a]` is skipped, like git does, instead
+            // of making the whole set fail to compile
+            if Regex::new(&regex).is_err() {
+                continue;
+            }
+            rules.push(rule);
+            regexes.push(regex);
+        }
+
+        // Every regex compiled on its own, so only a set too large for the regex crate fails
+        let set = RegexSet::new(&regexes).unwrap_or_else(|_| RegexSet::empty());
+
+        Gitignore {
+            root: root.to_path_buf(),
+            base: PathBuf::new(),
+            rules,
+            set,
+        }
+    }
+
+    /// Makes the rules apply to paths below `root`, a directory found at `base` relative to the
+    /// directory they were read from.
+    pub fn rebase(self, root: &Path, base: &Path) -> Gitignore {
+        Gitignore {
+            root: root.to_path_buf(),
+            base: base.to_path_buf(),
+            ..self
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns how the last rule matching `path` classifies it, gitignore-style.
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Match {
+        if self.is_empty() {
+            return Match::None;
+        }
+
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) => self.base.join(relative),
+            Err(_) => return Match::None,
+        };
+        let relative = relative.to_string_lossy();
+
+        let last = self
+            .set
+            .matches(&relative)
+            .iter()
+            .rev()
+            .find(|&index| is_dir || !self.rules[index].dir_only);
+
+        match last {
+            None => Match::None,
+            Some(index) if self.rules[index].negated => Match::Whitelist,
+            Some(_) => Match::Ignore,
+        }
+    }
+}
+
+/// Include and exclude globs given on the command line, matched gitignore-style against paths
+/// relative to the root being searched. Unlike in an ignore file, a glob includes what it
+/// matches and `!glob` excludes it; the last matching glob wins.
+#[derive(Debug, Clone, Default)]
+pub struct Globs {
+    rules: Vec<Rule>,
+    set: Option<RegexSet>,
+    has_includes: bool,
+}
+
+impl Globs {
+    /// Compiles `globs` in order, each with whether it ignores case.
+    pub fn new<'a>(globs: impl IntoIterator<Item = (&'a str, bool)>) -> Result<Globs, String> {
+        let mut rules = Vec::new();
+        let mut regexes = Vec::new();
+
+        for (glob, case_insensitive) in globs {
+            let Some((rule, regex)) = parse_line(glob) else {
+                continue;
+            };
+            let regex = if case_insensitive {
+                format!("(?i){regex}")
+            } else {
+                regex
+            };
+            // Catches invalid classes such as `[z-a]` with the glob that has them
+            Regex::new(&regex).map_err(|_| format!("invalid glob '{glob}'"))?;
+            rules.push(rule);
+            regexes.push(regex);
+        }
+
+        if rules.is_empty() {
+            return Ok(Globs::default());
+        }
+        let set = RegexSet::new(&regexes).map_err(|e| e.to_string())?;
+        let has_includes = rules.iter().any(|rule| !rule.negated);
+        Ok(Globs {
+            rules,
+            set: Some(set),
+            has_includes,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns whether the globs leave out `relative`, a path relative to the searched root.
+    ///
+    /// Once any glob includes files, files matching none are left out. Directories are only
+    /// left out by an exclude glob, so they are never read; they're also tested with a trailing
+    /// slash, so `!dir/**` leaves out `dir` itself.
+    pub fn is_excluded(&self, relative: &Path, is_dir: bool) -> bool {
+        let Some(set) = &self.set else {
+            return false;
+        };
+
+        let relative = relative.to_string_lossy();
+        let mut matches: Vec<usize> = set.matches(&relative).into_iter().collect();
+        if is_dir {
+            matches.extend(set.matches(&format!("{relative}/")));
+        }
+        let last = matches
+            .into_iter()
+            .filter(|&index| is_dir || !self.rules[index].dir_only)
+            .max();
+
+        match last {
+            Some(index) => self.rules[index].negated,
+            None => !is_dir && self.has_includes,
+        }
+    }
+}
+
+fn parse_line(line: &str) -> Option<(Rule, String)> {
+    let line = trim_trailing_spaces(line);
+
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (negated, line) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+
+    let (dir_only, line) = match line.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+
+    if line.is_empty() {
+        return None;
+    }
+
+    // A slash anywhere but at the end anchors the pattern to the ignore file's directory
+    let anchored = line.contains('/');
+    let line = line.strip_prefix('/').unwrap_or(line);
+
+    let mut regex = String::from("^");
+    if !anchored && !line.starts_with("**") {
+        regex.push_str("(?:.*/)?");
+    }
+    regex.push_str(&glob_to_regex(line));
+    regex.push('$');
+
+    Some((Rule { negated, dir_only }, regex))
+}
+
+fn trim_trailing_spaces(line: &str) -> &str {
+    let mut end = line.len();
+    while line[..end].ends_with(' ') {
+        // A space escaped with a backslash is kept
+        if line[..end - 1].ends_with('\\') {
+            break;
+        }
+        end -= 1;
+    }
+    &line[..end]
+}
+
+/// Translates a single gitignore-style glob into an unanchored regex fragment.
+pub fn glob_to_regex(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut regex = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let at_start = i == 0 || chars[i - 1] == '/';
+                let at_end = i + 2 == chars.len();
+                let before_slash = chars.get(i + 2) == Some(&'/');
+
+                if at_start && before_slash {
+                    // "**/" matches zero or more leading directories
+                    regex.push_str("(?:.*/)?");
+                    i += 3;
+                } else if at_start && at_end {
+                    // "/**" matches everything inside
+                    regex.push_str(".*");
+                    i += 2;
+                } else {
+                    // Any other "**" behaves like a regular "*"
+                    regex.push_str("[^/]*");
+                    i += 2;
+                }
+            }
+            '*' => {
+                regex.push_str("[^/]*");
+                i += 1;
+            }
+            '?' => {
+                regex.push_str("[^/]");
+                i += 1;
+            }
+            '[' => match parse_class(&chars[i..]) {
+                Some((class, consumed)) => {
+                    regex.push_str(&class);
+                    i += consumed;
+                }
+                None => {
+                    regex.push_str(r"\[");
+                    i += 1;
+                }
+            },
+            '\\' if i + 1 < chars.len() => {
+                regex.push_str(&regex::escape(&chars[i + 1].to_string()));
+                i += 2;
+            }
+            c => {
+                regex.push_str(&regex::escape(&c.to_string()));
+                i += 1;
+            }
+        }
+    }
+
+    regex
+}
+
+/// Parses a `[...]` character class starting at `chars[0]`, returning its regex and length.
+fn parse_class(chars: &[char]) -> Option<(String, usize)> {
+    let mut class = String::from("[");
+    let mut i = 1;
+
+    if matches!(chars.get(i), Some('!') | Some('^')) {
+        class.push('^');
+        i += 1;
+    }
+
+    let first = i;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == ']' && i > first {
+            class.push(']');
+            return Some((class, i + 1));
+        }
+        match c {
+            '\\' if i + 1 < chars.len() => {
+                let escaped = chars[i + 1];
+                if !escaped.is_alphanumeric() {
+                    class.push('\\');
+                }
+                class.push(escaped);
+                i += 2;
+                continue;
+            }
+            '[' | ']' | '&' | '~' | '^' => {
+                class.push('\\');
+                class.push(c);
+            }
+            c => class.push(c),
+        }
+        i += 1;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gitignore(contents: &str) -> Gitignore {
+        Gitignore::parse(Path::new("/root"), contents)
+    }
+
+    fn globs(globs: &[(&str, bool)]) -> Globs {
+        Globs::new(globs.iter().copied()).unwrap()
+    }
+
+    #[test]
+    fn matched_plain_name_at_any_depth() {
+        let ignore = gitignore("target\n");
+
+        assert_eq!(
+            ignore.matched(Path::new("/root/target"), true),
+            Match::Ignore
+        );
+        assert_eq!(
+            ignore.matched(Path::new("/root/a/b/target"), true),
+            Match::Ignore
+        );
+        assert_eq!(
+            ignore.matched(Path::new("/root/targets"), true),
+            Match::None
+        );
+    }
+
+    #[test]
+    fn matched_rebased_rules_see_the_path_from_their_directory() {
+        let ignore = gitignore("/src/gen/\n").rebase(Path::new("code"), Path::new("src"));
+
+        assert_eq!(ignore.matched(Path::new("code/gen"), true), Match::Ignore);
+        assert_eq!(ignore.matched(Path::new("code/a/gen"), true), Match::None);
+    }
+
+    #[test]
+    fn matched_skips_only_invalid_lines() {
+        let ignore = gitignore("target/\n[z-a]\n");
+
+        assert_eq!(
+            ignore.matched(Path::new("/root/target"), true),
+            Match::Ignore
+        );
+        assert_eq!(ignore.matched(Path::new("/root/z"), false), Match::None);
+    }
+
+    #[test]
+    fn matched_skips_comments_and_blank_lines() {
+        let ignore = gitignore("# target\n\n   \n");
+
+        assert!(ignore.is_empty());
+        assert_eq!(
+            ignore.matched(Path::new("/root/# target"), false),
+            Match::None
+        );
+    }
+
+    #[test]
+    fn matched_wildcard_does_not_cross_directories() {
+        let ignore = gitignore("*.log\n");
+
+        assert_eq!(
+            ignore.matched(Path::new("/root/logs/a.log"), false),
+            Match::Ignore
+        );
+        assert_eq!(ignore.matched(Path::new("/root/a.txt"), false), Match::None);
+    }
+
+    #[test]
+    fn matched_leading_slash_anchors_to_root() {
+        let ignore = gitignore("/build\n");
+
+        assert_eq!(
+            ignore.matched(Path::new("/root/build"), true),
+            Match::Ignore
+        );
+        assert_eq!(
+            ignore.matched(Path::new("/root/src/build"), true),
+            Match::None
+        );
+    }
+
+    #[test]
+    fn matched_middle_slash_anchors_to_root() {
+        let ignore = gitignore("docs/*.md\n");
+
+        assert_eq!(
+            ignore.matched(Path::new("/root/docs/a.md"), false),
+            Match::Ignore
+        );
+        assert_eq!(
+            ignore.matched(Path::new("/root/x/docs/a.md"), false),
+            Match::None
+        );
+    }
+
+    #[test]
+    fn matched_trailing_slash_only_matches_directories() {
+        let ignore = gitignore("out/\n");
+
+        assert_eq!(ignore.matched(Path::new("/root/out"), true), Match::Ignore);
+        assert_eq!(ignore.matched(Path::new("/root/out"), false), Match::None);
+    }
+
+    #[test]
+    fn matched_leading_double_star_matches_any_depth() {
+        let ignore = gitignore("**/gen/*.rs\n");
+
+        assert_eq!(
+            ignore.matched(Path::new("/root/gen/a.rs"), false),
+            Match::Ignore
+        );
+        assert_eq!(
+            ignore.matched(Path::new("/root/x/y/gen/a.rs"), false),
+            Match::Ignore
+        );
+    }
+
+    #[test]
+    fn matched_trailing_double_star_matches_contents_only() {
+        let ignore = gitignore("vendor/**\n");
+
+        assert_eq!(
+            ignore.matched(Path::new("/root/vendor/a/b.c"), false),
+            Match::Ignore
+        );
+        assert_eq!(ignore.matched(Path::new("/root/vendor"), true), Match::None);
+    }
+
+    #[test]
+    fn matched_middle_double_star_matches_zero_or_more_directories() {
+        let ignore = gitignore("a/**/b\n");
+
+        assert_eq!(ignore.matched(Path::new("/root/a/b"), false), Match::Ignore);
+        assert_eq!(
+            ignore.matched(Path::new("/root/a/x/y/b"), false),
+            Match::Ignore
+        );
+        assert_eq!(ignore.matched(Path::new("/root/a/xb"), false), Match::None);
+    }
+
+    #[test]
+    fn matched_negation_whitelists_and_last_rule_wins() {
+        let ignore = gitignore("*.log\n!keep.log\n");
+
+        assert_eq!(
+            ignore.matched(Path::new("/root/keep.log"), false),
+            Match::Whitelist
+        );
+        assert_eq!(
+            ignore.matched(Path::new("/root/drop.log"), false),
+            Match::Ignore
+        );
+
+        let ignore = gitignore("!keep.log\n*.log\n");
+
+        assert_eq!(
+            ignore.matched(Path::new("/root/keep.log"), false),
+            Match::Ignore
+        );
+    }
+
+    #[test]
+    fn matched_character_classes_and_question_mark() {
+        let ignore = gitignore("file[0-9].txt\nx?z\nno[!a].md\n");
+
+        assert_eq!(
+            ignore.matched(Path::new("/root/file7.txt"), false),
+            Match::Ignore
+        );
+        assert_eq!(
+            ignore.matched(Path::new("/root/filex.txt"), false),
+            Match::None
+        );
+        assert_eq!(ignore.matched(Path::new("/root/xyz"), false), Match::Ignore);
+        assert_eq!(
+            ignore.matched(Path::new("/root/nob.md"), false),
+            Match::Ignore
+        );
+        assert_eq!(
+            ignore.matched(Path::new("/root/noa.md"), false),
+            Match::None
+        );
+    }
+
+    #[test]
+    fn matched_escaped_special_characters_are_literal() {
+        let ignore = gitignore("\\#hash\n\\!bang\nstar\\*\n");
+
+        assert_eq!(
+            ignore.matched(Path::new("/root/#hash"), false),
+            Match::Ignore
+        );
+        assert_eq!(
+            ignore.matched(Path::new("/root/!bang"), false),
+            Match::Ignore
+        );
+        assert_eq!(
+            ignore.matched(Path::new("/root/star*"), false),
+            Match::Ignore
+        );
+        assert_eq!(
+            ignore.matched(Path::new("/root/starry"), false),
+            Match::None
+        );
+    }
+
+    #[test]
+    fn matched_paths_outside_root_are_not_matched() {
+        let ignore = gitignore("*\n");
+
+        assert_eq!(ignore.matched(Path::new("/other/a"), false), Match::None);
+    }
+
+    #[test]
+    fn globs_later_globs_override_earlier_ones() {
+        let globs = globs(&[("*.rs", false), ("!**/generated/**", false)]);
+
+        assert!(!globs.is_excluded(Path::new("src/main.rs"), false));
+        assert!(globs.is_excluded(Path::new("src/main.c"), false));
+        assert!(globs.is_excluded(Path::new("src/generated/a.rs"), false));
+        // Include globs don't leave directories out, exclude globs prune them
+        assert!(!globs.is_excluded(Path::new("src"), true));
+        assert!(globs.is_excluded(Path::new("src/generated"), true));
+
+        let globs = self::globs(&[("!*.rs", false), ("keep.rs", false)]);
+        assert!(!globs.is_excluded(Path::new("a/keep.rs"), false));
+        assert!(globs.is_excluded(Path::new("a/other.rs"), false));
+    }
+
+    #[test]
+    fn globs_case_insensitive_only_where_asked() {
+        let globs = globs(&[("*.RS", true), ("*.TXT", false)]);
+
+        assert!(!globs.is_excluded(Path::new("a.rs"), false));
+        assert!(globs.is_excluded(Path::new("a.txt"), false));
+        assert!(!globs.is_excluded(Path::new("a.TXT"), false));
+    }
+
+    #[test]
+    fn globs_reports_invalid_globs() {
+        assert_eq!(
+            Globs::new([("[z-a]", false)]).unwrap_err(),
+            "invalid glob '[z-a]'"
+        );
+        assert!(Globs::new([]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+use crate::searcher::{Span, Stats};
+use std::fmt::Write;
+use std::str;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Returns `value` as a quoted JSON string.
+pub fn string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Returns standard, padded base64 for `bytes`.
+pub fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | (byte as u32) << (16 - 8 * i)
+        });
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (group >> (18 - 6 * i)) & 0x3f;
+                encoded.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Returns `{"text":...}` for UTF-8 data, or `{"bytes":...}` with base64 so nothing is lost.
+pub fn data(bytes: &[u8]) -> String {
+    match str::from_utf8(bytes) {
+        Ok(text) => format!("{{\"text\":{}}}", string(text)),
+        Err(_) => format!("{{\"bytes\":{}}}", string(&base64(bytes))),
+    }
+}
+
+pub fn begin(path: &[u8]) -> String {
+    format!(
+        "{{\"type\":\"begin\",\"data\":{{\"path\":{}}}}}",
+        data(path)
+    )
+}
+
+/// Returns a `match` or `context` event, depending on `kind`, for a line of `path`.
+pub fn line(
+    kind: &str,
+    path: &[u8],
+    line: &[u8],
+    number: usize,
+    offset: usize,
+    spans: &[Span],
+) -> String {
+    let submatches: Vec<String> = spans
+        .iter()
+        .map(|span| {
+            format!(
+                "{{\"match\":{},\"start\":{},\"end\":{}}}",
+                data(&line[span.clone()]),
+                span.start,
+                span.end
+            )
+        })
+        .collect();
+
+    format!(
+        "{{\"type\":{},\"data\":{{\"path\":{},\"lines\":{},\"line_number\":{},\"absolute_offset\":{},\"submatches\":[{}]}}}}",
+        string(kind),
+        data(path),
+        data(line),
+        number,
+        offset,
+        submatches.join(",")
+    )
+}
+
+fn stats(stats: &Stats) -> String {
+    format!(
+        "{{\"searches\":{},\"searches_with_match\":{},\"bytes_searched\":{},\"matched_lines\":{},\"matches\":{}}}",
+        stats.searches,
+        stats.searches_with_match,
+        stats.bytes_searched,
+        stats.matched_lines,
+        stats.matches
+    )
+}
+
+pub fn end(path: &[u8], file_stats: &Stats) -> String {
+    format!(
+        "{{\"type\":\"end\",\"data\":{{\"path\":{},\"stats\":{}}}}}",
+        data(path),
+        stats(file_stats)
+    )
+}
+
+pub fn summary(total_stats: &Stats) -> String {
+    format!(
+        "{{\"type\":\"summary\",\"data\":{{\"stats\":{}}}}}",
+        stats(total_stats)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_escapes_quotes_backslashes_and_control_characters() {
+        assert_eq!(string("a \"b\" \\ c"), r#""a \"b\" \\ c""#);
+        assert_eq!(string("tab\tnew\nline\u{1}"), r#""tab\tnew\nline\u0001""#);
+        assert_eq!(string("héllo"), "\"héllo\"");
+    }
+
+    #[test]
+    fn base64_pads_incomplete_groups() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xff, 0xfe]), "//4=");
+    }
+
+    #[test]
+    fn data_falls_back_to_base64_for_invalid_utf8() {
+        assert_eq!(data(b"ok"), r#"{"text":"ok"}"#);
+        assert_eq!(data(&[b'a', 0xff]), r#"{"bytes":"Yf8="}"#);
+    }
+
+    #[test]
+    fn line_lists_submatches() {
+        let span = 4..6;
+        let event = line(
+            "match",
+            b"a.txt",
+            b"say hi",
+            3,
+            10,
+            std::slice::from_ref(&span),
+        );
+
+        assert_eq!(
+            event,
+            r#"{"type":"match","data":{"path":{"text":"a.txt"},"lines":{"text":"say hi"},"line_number":3,"absolute_offset":10,"submatches":[{"match":{"text":"hi"},"start":4,"end":6}]}}"#
+        );
+    }
+}
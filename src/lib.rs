@@ -1,50 +1,310 @@
-use crate::scanner::print_matches;
-use crate::walker::collect_files;
-use regex::Regex;
-use std::path::PathBuf;
+use crate::color::{ColorChoice, Colors};
+use crate::error::ErrorReporter;
+pub use crate::error::{Error, ErrorKind};
+use crate::ignore::Globs;
+pub use crate::matcher::{
+    CaseInsensitiveMatcher, LiteralMatcher, Matcher, MultiMatcher, RegexMatcher, WordMatcher,
+};
+pub use crate::scanner::OutputMode;
+use crate::scanner::{
+    STDIN_LABEL, ScanOptions, SharedStdout, print_file_matches, print_matches, print_stdin_matches,
+};
+pub use crate::searcher::{Context, Match, MmapChoice, Searcher, Sink, Span, Stats};
+pub use crate::walker::SortBy;
+use crate::walker::{WalkOptions, dedup_roots, sort_files, walk_parallel};
+use std::io;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
 
+pub mod args;
+pub mod color;
+pub mod error;
+pub mod ignore;
+pub mod json;
+pub mod matcher;
 pub mod scanner;
+pub mod searcher;
 pub mod sniff;
 pub mod walker;
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CaseMode {
+    #[default]
+    Sensitive,
+    Insensitive,
+    /// Insensitive unless the pattern contains an uppercase letter
+    Smart,
+}
+
+pub const STDIN_PATH: &str = "-";
+
+#[derive(Default)]
 pub struct Config {
     pub regex_mode: bool,
-    pub pattern: String,
-    pub path: PathBuf,
+    pub case_mode: CaseMode,
+    /// Select lines that don't match
+    pub invert_match: bool,
+    /// Only match whole words
+    pub word_regexp: bool,
+    /// Only match whole lines
+    pub line_regexp: bool,
+    /// A line matches if any of these patterns matches it
+    pub patterns: Vec<String>,
+    /// Paths to search, where `-` stands for standard input
+    pub paths: Vec<PathBuf>,
+    /// Name shown for matches read from standard input
+    pub label: Option<String>,
+    /// Don't respect ignore files, nor skip `.git` directories
+    pub no_ignore: bool,
+    /// Search hidden files and directories
+    pub hidden: bool,
+    /// Search binary files as if they were text
+    pub binary: bool,
+    /// Follow symbolic links while walking directories
+    pub follow: bool,
+    /// Include and exclude globs, later ones overriding earlier ones
+    pub globs: Globs,
+    pub before_context: usize,
+    pub after_context: usize,
+    pub output_mode: OutputMode,
+    /// Print each match on its own line instead of the whole line
+    pub only_matching: bool,
+    /// Print the 1-based column of the first match on each line
+    pub column: bool,
+    pub color: ColorChoice,
+    pub colors: Colors,
+    pub sort: SortBy,
+    /// `None` prints headings only when stdout is a terminal
+    pub heading: Option<bool>,
+    pub json: bool,
+    pub mmap: MmapChoice,
+    /// Don't print errors as they happen; they still make the exit code 2
+    pub no_messages: bool,
+    /// Print the number of errors per category once the search is done
+    pub error_summary: bool,
 }
 
-pub fn run(config: Config) -> i32 {
-    let files = match collect_files(&config.path) {
-        Ok(files) => files,
+fn has_uppercase(pattern: &str, regex_mode: bool) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if regex_mode && c == '\\' {
+            // Escapes such as \S or \W are classes, not literal uppercase letters
+            chars.next();
+            continue;
+        }
+        if c.is_uppercase() {
+            return true;
+        }
+    }
+    false
+}
+
+fn is_case_insensitive(config: &Config) -> bool {
+    match config.case_mode {
+        CaseMode::Sensitive => false,
+        CaseMode::Insensitive => true,
+        CaseMode::Smart => !config
+            .patterns
+            .iter()
+            .any(|pattern| has_uppercase(pattern, config.regex_mode)),
+    }
+}
+
+fn build_matcher(config: &Config) -> Result<Box<dyn Matcher + Send + Sync>, regex::Error> {
+    let case_insensitive = is_case_insensitive(config);
+    let plain = !config.regex_mode && !config.word_regexp && !config.line_regexp;
+
+    let matcher: Box<dyn Matcher + Send + Sync> = match config.patterns.as_slice() {
+        // Only possible with an empty pattern file, which matches nothing
+        [] => Box::new(MultiMatcher::new(Vec::new())),
+        [pattern] if plain && case_insensitive => Box::new(CaseInsensitiveMatcher::new(pattern)),
+        [pattern] if plain => Box::new(LiteralMatcher::new(pattern)),
+        patterns => {
+            // All patterns go into one alternation so each line is scanned once. Alternations
+            // of literals are searched with a multi-pattern automaton by the regex engine.
+            // Literals are escaped so the regex engine can apply Unicode case folding to them.
+            let alternatives: Vec<String> = patterns
+                .iter()
+                .map(|pattern| {
+                    if config.regex_mode {
+                        format!("(?:{pattern})")
+                    } else {
+                        regex::escape(pattern)
+                    }
+                })
+                .collect();
+            let pattern = alternatives.join("|");
+            if config.line_regexp {
+                Box::new(RegexMatcher::new(
+                    &format!("^(?:{pattern})$"),
+                    case_insensitive,
+                )?)
+            } else if config.word_regexp {
+                Box::new(WordMatcher::new(&pattern, case_insensitive)?)
+            } else {
+                Box::new(RegexMatcher::new(&pattern, case_insensitive)?)
+            }
+        }
+    };
+    Ok(matcher)
+}
+
+/// What a search found: whether any line matched, and every error reported along the way.
+#[derive(Debug)]
+pub struct SearchOutcome {
+    pub had_match: bool,
+    pub errors: Vec<Error>,
+}
+
+impl SearchOutcome {
+    /// Returns 2 if any error occurred, otherwise 0 if something matched and 1 if not.
+    pub fn exit_code(&self) -> i32 {
+        if !self.errors.is_empty() {
+            2
+        } else if self.had_match {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+pub fn search(config: &Config) -> SearchOutcome {
+    let errors = ErrorReporter::new(config.no_messages);
+    let mut roots = Vec::new();
+    let mut search_stdin = false;
+
+    for root in dedup_roots(&config.paths) {
+        if root == Path::new(STDIN_PATH) {
+            search_stdin = true;
+            continue;
+        }
+        // Missing roots are reported before anything else, even an invalid pattern
+        match root.metadata() {
+            Ok(_) => roots.push(root),
+            Err(e) => errors.report(Error::io(root, e)),
+        }
+    }
+
+    let matcher = match build_matcher(config) {
+        Ok(matcher) => matcher,
         Err(e) => {
-            eprintln!("{e}");
-            return 2;
+            errors.report(Error::InvalidPattern(e));
+            return SearchOutcome {
+                had_match: false,
+                errors: errors.into_errors(),
+            };
         }
     };
 
-    let matcher: Box<dyn Fn(&str) -> bool + Send + Sync> = if config.regex_mode {
-        let regex = match Regex::new(&config.pattern) {
-            Ok(regex) => regex,
-            Err(e) => {
-                eprintln!("Regex not valid: {}", e);
-                return 2;
-            }
-        };
-        Box::new(move |line| regex.is_match(line))
-    } else {
-        Box::new(move |line| line.contains(&config.pattern))
+    let scan_options = ScanOptions {
+        output_mode: config.output_mode,
+        only_matching: config.only_matching,
+        column: config.column,
+        // JSON is never highlighted nor grouped under headings
+        colors: (config.color.enabled() && !config.json).then_some(config.colors),
+        ordered: config.sort != SortBy::None,
+        // Headings are meant for people, so by default they're only used on a terminal
+        heading: config.heading.unwrap_or_else(|| io::stdout().is_terminal()),
+        json: config.json,
     };
+    let stdout = SharedStdout::new(&scan_options);
 
-    let (had_match, had_error) = print_matches(matcher.as_ref(), &files);
-    if had_error {
-        2
-    } else if had_match {
-        0
+    // Context makes no sense when only the matched parts are printed, but JSON ignores -o
+    let searcher = if config.only_matching && !config.json {
+        Searcher::new(matcher)
     } else {
-        1
+        Searcher::new(matcher).with_context(config.before_context, config.after_context)
+    };
+    let searcher = searcher
+        .invert_match(config.invert_match)
+        .binary(config.binary)
+        .mmap(mmap_choice(config.mmap, &roots));
+
+    let mut had_match = match single_file(&roots, search_stdin) {
+        // Nothing to walk nor to keep apart, so matches are printed as soon as they're found
+        Some(path) => print_file_matches(&searcher, path, &scan_options, &stdout, &errors),
+        None => search_files(config, &roots, &searcher, &scan_options, &stdout, &errors),
+    };
+
+    if search_stdin && !stdout.failed() {
+        let label = config.label.as_deref().unwrap_or(STDIN_LABEL);
+        had_match |= print_stdin_matches(&searcher, label, &scan_options, &stdout, &errors);
+    }
+    if let Err(e) = stdout.finish() {
+        stdout.report_error(e, &errors);
+    }
+
+    SearchOutcome {
+        had_match,
+        errors: errors.into_errors(),
+    }
+}
+
+/// Returns the root to search if it's a single file and the only input.
+fn single_file(roots: &[PathBuf], search_stdin: bool) -> Option<&Path> {
+    match roots {
+        [root] if !search_stdin && root.is_file() => Some(root),
+        _ => None,
+    }
+}
+
+/// Returns how files are searched. `MmapChoice::Auto` only maps the files named as roots: a
+/// walk may find files that other processes are rewriting, and truncating a mapped file kills
+/// the search with `SIGBUS`.
+fn mmap_choice(choice: MmapChoice, roots: &[PathBuf]) -> MmapChoice {
+    match choice {
+        MmapChoice::Auto if roots.iter().any(|root| root.is_dir()) => MmapChoice::Never,
+        choice => choice,
     }
 }
 
+/// Walks `roots` and searches the files found in parallel, returning whether any matched.
+fn search_files<M: Matcher + Sync>(
+    config: &Config,
+    roots: &[PathBuf],
+    searcher: &Searcher<M>,
+    scan_options: &ScanOptions,
+    stdout: &SharedStdout,
+    errors: &ErrorReporter,
+) -> bool {
+    let walk_options = WalkOptions {
+        no_ignore: config.no_ignore,
+        hidden: config.hidden,
+        follow: config.follow,
+        globs: config.globs.clone(),
+    };
+
+    let (files, found_files) = mpsc::channel();
+    thread::scope(|scope| {
+        let walk_options = &walk_options;
+        scope.spawn(move || walk_parallel(roots, walk_options, errors, &files));
+
+        if config.sort == SortBy::None {
+            // Files are searched as soon as the walk finds them
+            let files = found_files.into_iter().enumerate();
+            print_matches(searcher, files, scan_options, stdout, errors)
+        } else {
+            let mut files: Vec<PathBuf> = found_files.into_iter().collect();
+            sort_files(&mut files, config.sort);
+            let files = files.into_iter().enumerate();
+            print_matches(searcher, files, scan_options, stdout, errors)
+        }
+    })
+}
+
+pub fn run(config: Config) -> i32 {
+    let outcome = search(&config);
+
+    if config.error_summary && !outcome.errors.is_empty() {
+        eprintln!("rgrep: {}", error::summary(&outcome.errors));
+    }
+
+    outcome.exit_code()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -53,6 +313,217 @@ mod tests {
     use std::os::unix::fs::PermissionsExt;
     use tempfile::tempdir;
 
+    #[test]
+    fn has_uppercase_detects_uppercase_letters() {
+        assert!(has_uppercase("Hello", false));
+        assert!(has_uppercase("ÉCOLE", false));
+        assert!(!has_uppercase("hello world", false));
+    }
+
+    #[test]
+    fn has_uppercase_ignores_regex_escapes() {
+        assert!(!has_uppercase(r"\Sfoo\W", true));
+        assert!(has_uppercase(r"\SFoo", true));
+
+        // Outside regex mode a backslash is just a literal character
+        assert!(has_uppercase(r"\S", false));
+    }
+
+    #[test]
+    fn is_case_insensitive_follows_case_mode() {
+        let config = |case_mode, pattern: &str| Config {
+            case_mode,
+            patterns: vec![pattern.to_string()],
+            ..Config::default()
+        };
+
+        assert!(!is_case_insensitive(&config(CaseMode::Sensitive, "hello")));
+        assert!(is_case_insensitive(&config(CaseMode::Insensitive, "Hello")));
+        assert!(is_case_insensitive(&config(CaseMode::Smart, "hello")));
+        assert!(!is_case_insensitive(&config(CaseMode::Smart, "Hello")));
+    }
+
+    #[test]
+    fn mmap_choice_only_maps_roots_named_as_files_by_default() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("a.log");
+        File::create(&file).unwrap();
+        let files = vec![file.clone()];
+        let walked = vec![file, dir.path().to_path_buf()];
+
+        assert_eq!(mmap_choice(MmapChoice::Auto, &files), MmapChoice::Auto);
+        assert_eq!(mmap_choice(MmapChoice::Auto, &walked), MmapChoice::Never);
+        assert_eq!(mmap_choice(MmapChoice::Always, &walked), MmapChoice::Always);
+        assert_eq!(mmap_choice(MmapChoice::Never, &files), MmapChoice::Never);
+    }
+
+    /// Returns the spans the searcher would report for `line`.
+    fn spans(config: Config, line: &str) -> Vec<Span> {
+        let mut spans = Vec::new();
+        build_matcher(&config)
+            .unwrap()
+            .find_iter(line.as_bytes(), &mut spans);
+        if config.invert_match {
+            spans.clear();
+        }
+        spans
+    }
+
+    /// Returns whether the searcher would select `line`.
+    fn matches(config: Config, line: &str) -> bool {
+        let mut spans = Vec::new();
+        build_matcher(&config)
+            .unwrap()
+            .find_iter(line.as_bytes(), &mut spans);
+        spans.is_empty() == config.invert_match
+    }
+
+    #[test]
+    fn build_matcher_finds_every_match_span_in_a_line() {
+        let config = |regex_mode, word_regexp, pattern: &str| Config {
+            regex_mode,
+            word_regexp,
+            patterns: vec![pattern.to_string()],
+            ..Config::default()
+        };
+
+        assert_eq!(
+            spans(config(false, false, "ab"), "ab ab abab"),
+            vec![0..2, 3..5, 6..8, 8..10]
+        );
+        assert_eq!(spans(config(true, false, "a+"), "aa b a"), vec![0..2, 5..6]);
+        assert_eq!(
+            spans(config(false, true, "foo"), "foo foo,foo foobar"),
+            vec![0..3, 4..7, 8..11]
+        );
+        assert!(spans(config(false, false, "x"), "yyy").is_empty());
+    }
+
+    #[test]
+    fn build_matcher_case_insensitive_spans_cover_original_text() {
+        let config = Config {
+            case_mode: CaseMode::Insensitive,
+            patterns: vec!["straße".to_string()],
+            ..Config::default()
+        };
+
+        assert_eq!(spans(config, "x STRAẞE"), vec![2..10]);
+    }
+
+    #[test]
+    fn build_matcher_inverted_match_has_no_spans() {
+        let config = || Config {
+            invert_match: true,
+            patterns: vec!["x".to_string()],
+            ..Config::default()
+        };
+
+        assert!(matches(config(), "abc abc"));
+        assert!(spans(config(), "abc abc").is_empty());
+        assert!(!matches(config(), "x"));
+    }
+
+    #[test]
+    fn build_matcher_invert_match_selects_non_matching_lines() {
+        let config = || Config {
+            invert_match: true,
+            patterns: vec!["Hello".to_string()],
+            ..Config::default()
+        };
+
+        assert!(!matches(config(), "Hello World"));
+        assert!(matches(config(), "Goodbye World"));
+    }
+
+    #[test]
+    fn build_matcher_word_regexp_requires_word_boundaries() {
+        let config = |regex_mode, pattern: &str| Config {
+            regex_mode,
+            word_regexp: true,
+            patterns: vec![pattern.to_string()],
+            ..Config::default()
+        };
+
+        assert!(matches(config(false, "foo"), "a foo b"));
+        assert!(matches(config(false, "foo"), "foo"));
+        assert!(matches(config(false, "foo"), "(foo)"));
+        assert!(!matches(config(false, "foo"), "foobar"));
+        assert!(!matches(config(false, "foo"), "a_foo"));
+        assert!(matches(config(false, "-x"), "a -x b"));
+        assert!(matches(config(true, "fo+|bar"), "x bar"));
+        assert!(!matches(config(true, "fo+|bar"), "xbar"));
+    }
+
+    #[test]
+    fn build_matcher_line_regexp_requires_whole_line() {
+        let config = |regex_mode, pattern: &str| Config {
+            regex_mode,
+            line_regexp: true,
+            patterns: vec![pattern.to_string()],
+            ..Config::default()
+        };
+
+        assert!(matches(config(false, "a.b"), "a.b"));
+        assert!(!matches(config(false, "a.b"), "a.b c"));
+        assert!(!matches(config(false, "a.b"), "axb"));
+        assert!(matches(config(true, "a|b.*"), "bcd"));
+        assert!(!matches(config(true, "a|b.*"), "ab"));
+    }
+
+    #[test]
+    fn build_matcher_matches_any_pattern() {
+        let config = |regex_mode, patterns: &[&str]| Config {
+            regex_mode,
+            patterns: patterns.iter().map(|pattern| pattern.to_string()).collect(),
+            ..Config::default()
+        };
+
+        assert!(matches(config(false, &["foo", "a.c"]), "x a.c"));
+        assert!(matches(config(false, &["foo", "a.c"]), "foo"));
+        assert!(!matches(config(false, &["foo", "a.c"]), "abc"));
+        assert!(matches(config(true, &["^x", "y$"]), "ay"));
+        assert!(!matches(config(true, &["^x", "y$"]), "ya"));
+        assert!(!matches(config(false, &[]), "anything"));
+    }
+
+    #[test]
+    fn build_matcher_line_regexp_applies_to_each_pattern() {
+        let config = || Config {
+            regex_mode: true,
+            line_regexp: true,
+            patterns: vec!["a".to_string(), "b+".to_string()],
+            ..Config::default()
+        };
+
+        assert!(matches(config(), "a"));
+        assert!(matches(config(), "bbb"));
+        assert_eq!(spans(config(), "bbb"), vec![0..3]);
+        assert!(!matches(config(), "ab"));
+    }
+
+    #[test]
+    fn is_case_insensitive_smart_case_checks_every_pattern() {
+        let config = |patterns: &[&str]| Config {
+            case_mode: CaseMode::Smart,
+            patterns: patterns.iter().map(|pattern| pattern.to_string()).collect(),
+            ..Config::default()
+        };
+
+        assert!(is_case_insensitive(&config(&["foo", "bar"])));
+        assert!(!is_case_insensitive(&config(&["foo", "Bar"])));
+    }
+
+    #[test]
+    fn build_matcher_invalid_regex_is_an_error() {
+        let config = Config {
+            regex_mode: true,
+            patterns: vec!["(".to_string()],
+            ..Config::default()
+        };
+
+        assert!(build_matcher(&config).is_err());
+    }
+
     #[test]
     fn run_returns_zero_when_any_match_found() {
         // Create root folder
@@ -78,8 +549,9 @@ mod tests {
         // Create the desired config
         let config = Config {
             regex_mode: false,
-            pattern: "Hello".to_string(),
-            path: root_path.to_path_buf(),
+            patterns: vec!["Hello".to_string()],
+            paths: vec![root_path.to_path_buf()],
+            ..Config::default()
         };
 
         let exit_code = run(config);
@@ -113,8 +585,9 @@ mod tests {
         // Create the desired config
         let config = Config {
             regex_mode: false,
-            pattern: "Hello".to_string(),
-            path: root_path.to_path_buf(),
+            patterns: vec!["Hello".to_string()],
+            paths: vec![root_path.to_path_buf()],
+            ..Config::default()
         };
 
         let exit_code = run(config);
@@ -154,8 +627,9 @@ mod tests {
         // Create the desired config
         let config = Config {
             regex_mode: false,
-            pattern: "Hello".to_string(),
-            path: root_path.to_path_buf(),
+            patterns: vec!["Hello".to_string()],
+            paths: vec![root_path.to_path_buf()],
+            ..Config::default()
         };
 
         let exit_code = run(config);
@@ -163,4 +637,26 @@ mod tests {
         // Assert we expect exit_code 2, because there was at least 1 error
         assert_eq!(exit_code, 2);
     }
+
+    #[test]
+    fn search_collects_categorized_errors() {
+        // Create root folder
+        let root = tempdir().unwrap();
+        let missing_path = root.path().join("missing");
+
+        let config = Config {
+            patterns: vec!["(".to_string()],
+            regex_mode: true,
+            paths: vec![missing_path.clone()],
+            no_messages: true,
+            ..Config::default()
+        };
+
+        let outcome = search(&config);
+
+        let kinds: Vec<ErrorKind> = outcome.errors.iter().map(Error::kind).collect();
+        assert_eq!(kinds, vec![ErrorKind::NotFound, ErrorKind::InvalidPattern]);
+        assert_eq!(outcome.errors[0].path(), Some(missing_path.as_path()));
+        assert_eq!(outcome.exit_code(), 2);
+    }
 }
@@ -1,47 +1,22 @@
-use rgrep::{Config, run};
+use rgrep::args::{Action, USAGE, help, parse_args, stdin_is_readable};
+use rgrep::run;
 use std::env;
-use std::path::PathBuf;
 use std::process::exit;
 
-fn parse_args() -> Result<Config, String> {
-    let usage = "Usage: rgrep [--regex] <pattern> <path>".to_string();
-
-    let mut args = env::args();
-
-    args.next(); // Skip executable path
-
-    // read first argument
-    let pattern_or_regex_mode = args.next().ok_or(usage.clone())?;
-
-    // find out if user wants regex search
-    let regex_mode = pattern_or_regex_mode == "--regex";
-
-    let pattern = if regex_mode {
-        // if user wants regex search we must read the second argument
-        args.next().ok_or(usage.clone())?
-    } else {
-        pattern_or_regex_mode
-    };
-
-    // read third argument
-    let path = args.next().map(|s| PathBuf::from(s)).ok_or(usage)?;
-
-    let config = Config {
-        regex_mode,
-        pattern,
-        path,
-    };
-
-    Ok(config)
-}
-
 fn main() {
-    let config = match parse_args() {
-        Ok(config) => config,
+    let action = match parse_args(env::args().skip(1), stdin_is_readable()) {
+        Ok(action) => action,
         Err(e) => {
-            eprintln!("{e}");
+            eprintln!("rgrep: {e}");
+            eprintln!("{USAGE}");
+            eprintln!("\nFor more information try --help");
             exit(2);
         }
     };
-    exit(run(config));
+
+    match action {
+        Action::Help => print!("{}", help()),
+        Action::Version => println!("rgrep {}", env!("CARGO_PKG_VERSION")),
+        Action::Search(config) => exit(run(*config)),
+    }
 }
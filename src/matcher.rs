@@ -0,0 +1,340 @@
+use crate::searcher::Span;
+use memchr::memmem;
+use regex::bytes::{Regex, RegexBuilder};
+
+/// Finds matches in a haystack of bytes. The `Searcher` only talks to this trait, so a new
+/// matching strategy only needs an implementation of it.
+///
+/// The searcher first runs `find_at` over many lines at once to find the next line that may
+/// match, then `find_iter` over that line alone. A match found in a single line must therefore
+/// also be found in a haystack holding that line between `\n` or `\r\n` terminators; matches
+/// spanning several lines are harmless.
+pub trait Matcher {
+    /// Returns the first match starting at or after `at`. Like the regex crate, anchors such as
+    /// `^` or `\b` still see the bytes before `at`.
+    fn find_at(&self, haystack: &[u8], at: usize) -> Option<Span>;
+
+    /// Returns the capture groups of the first match starting at or after `at`, the whole match
+    /// being group 0. Matchers without capture groups return `None`.
+    fn captures_at(&self, _haystack: &[u8], _at: usize) -> Option<Vec<Option<Span>>> {
+        None
+    }
+
+    /// Returns bytes that every match contains, so callers can skip haystacks without them.
+    fn literal_prefilter(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Appends the span of every non-overlapping match in `haystack` to `spans`.
+    fn find_iter(&self, haystack: &[u8], spans: &mut Vec<Span>) {
+        let mut at = 0;
+        let mut last_end = None;
+        while at <= haystack.len() {
+            let Some(span) = self.find_at(haystack, at) else {
+                break;
+            };
+            at = if span.is_empty() {
+                // Skip a whole character so an empty match can't be found twice, nor split one
+                span.end + haystack.get(span.end).map_or(1, |&byte| utf8_len(byte))
+            } else {
+                span.end
+            };
+            // Like the regex crate, an empty match right after the previous match isn't one
+            if span.is_empty() && last_end == Some(span.start) {
+                continue;
+            }
+            last_end = Some(span.end);
+            spans.push(span);
+        }
+    }
+}
+
+impl<M: Matcher + ?Sized> Matcher for Box<M> {
+    fn find_at(&self, haystack: &[u8], at: usize) -> Option<Span> {
+        (**self).find_at(haystack, at)
+    }
+
+    fn captures_at(&self, haystack: &[u8], at: usize) -> Option<Vec<Option<Span>>> {
+        (**self).captures_at(haystack, at)
+    }
+
+    fn literal_prefilter(&self) -> Option<&[u8]> {
+        (**self).literal_prefilter()
+    }
+
+    fn find_iter(&self, haystack: &[u8], spans: &mut Vec<Span>) {
+        (**self).find_iter(haystack, spans)
+    }
+}
+
+/// Returns the length of the UTF-8 sequence starting with `byte`, or 1 for a stray byte.
+fn utf8_len(byte: u8) -> usize {
+    match byte {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    }
+}
+
+/// Matches a string exactly.
+#[derive(Debug, Clone)]
+pub struct LiteralMatcher {
+    finder: memmem::Finder<'static>,
+}
+
+impl LiteralMatcher {
+    pub fn new(literal: &str) -> LiteralMatcher {
+        LiteralMatcher {
+            finder: memmem::Finder::new(literal.as_bytes()).into_owned(),
+        }
+    }
+}
+
+impl Matcher for LiteralMatcher {
+    fn find_at(&self, haystack: &[u8], at: usize) -> Option<Span> {
+        let start = at + self.finder.find(haystack.get(at..)?)?;
+        Some(start..start + self.finder.needle().len())
+    }
+
+    fn literal_prefilter(&self) -> Option<&[u8]> {
+        Some(self.finder.needle())
+    }
+}
+
+/// Matches a string regardless of case, following Unicode case folding.
+#[derive(Debug, Clone)]
+pub struct CaseInsensitiveMatcher {
+    search: CaseInsensitiveSearch,
+}
+
+#[derive(Debug, Clone)]
+enum CaseInsensitiveSearch {
+    /// The lowercase literal, compared byte by byte
+    Ascii(Vec<u8>),
+    Regex(RegexMatcher),
+}
+
+impl CaseInsensitiveMatcher {
+    pub fn new(literal: &str) -> CaseInsensitiveMatcher {
+        // The Kelvin sign and the long s fold to k and s, so only other ASCII literals can
+        // ignore non-ASCII text
+        let ascii = literal.is_ascii()
+            && !literal.is_empty()
+            && !literal
+                .bytes()
+                .any(|b| matches!(b, b'k' | b'K' | b's' | b'S'));
+        let search = if ascii {
+            CaseInsensitiveSearch::Ascii(literal.to_ascii_lowercase().into_bytes())
+        } else {
+            let regex = RegexMatcher::new(&regex::escape(literal), true)
+                .expect("an escaped literal is a valid regex");
+            CaseInsensitiveSearch::Regex(regex)
+        };
+        CaseInsensitiveMatcher { search }
+    }
+}
+
+impl Matcher for CaseInsensitiveMatcher {
+    fn find_at(&self, haystack: &[u8], at: usize) -> Option<Span> {
+        let literal = match &self.search {
+            CaseInsensitiveSearch::Ascii(literal) => literal,
+            CaseInsensitiveSearch::Regex(regex) => return regex.find_at(haystack, at),
+        };
+
+        let first = literal[0];
+        let mut start = at;
+        while start + literal.len() <= haystack.len() {
+            start += memchr::memchr2(first, first.to_ascii_uppercase(), &haystack[start..])?;
+            let candidate = haystack.get(start..start + literal.len())?;
+            if candidate.eq_ignore_ascii_case(literal) {
+                return Some(start..start + literal.len());
+            }
+            start += 1;
+        }
+        None
+    }
+}
+
+/// Matches a regular expression, with its capture groups. `^` and `$` match at the start and
+/// end of every line of the haystack.
+#[derive(Debug, Clone)]
+pub struct RegexMatcher {
+    regex: Regex,
+}
+
+impl RegexMatcher {
+    pub fn new(pattern: &str, case_insensitive: bool) -> Result<RegexMatcher, regex::Error> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(case_insensitive)
+            .multi_line(true)
+            .crlf(true)
+            .build()?;
+        Ok(RegexMatcher { regex })
+    }
+}
+
+impl Matcher for RegexMatcher {
+    fn find_at(&self, haystack: &[u8], at: usize) -> Option<Span> {
+        self.regex.find_at(haystack, at).map(|found| found.range())
+    }
+
+    fn captures_at(&self, haystack: &[u8], at: usize) -> Option<Vec<Option<Span>>> {
+        let captures = self.regex.captures_at(haystack, at)?;
+        Some(
+            captures
+                .iter()
+                .map(|group| group.map(|group| group.range()))
+                .collect(),
+        )
+    }
+}
+
+/// Matches whole words of a regular expression: matches must not be preceded nor followed by a
+/// word character.
+#[derive(Debug, Clone)]
+pub struct WordMatcher {
+    /// The pattern as `(?:^|\W)(pattern)(?:\W|$)`, the word being group 1
+    regex: RegexMatcher,
+}
+
+impl WordMatcher {
+    pub fn new(pattern: &str, case_insensitive: bool) -> Result<WordMatcher, regex::Error> {
+        // Unlike \b, this also works for patterns starting or ending with non-word characters
+        let regex = RegexMatcher::new(&format!(r"(?:^|\W)({pattern})(?:\W|$)"), case_insensitive)?;
+        Ok(WordMatcher { regex })
+    }
+}
+
+impl Matcher for WordMatcher {
+    /// Returns the word itself rather than the non-word characters around it, so a search
+    /// resuming after it also finds an adjacent word, as in "foo foo".
+    fn find_at(&self, haystack: &[u8], at: usize) -> Option<Span> {
+        // Start at the character before `at`, which may be the non-word character in front of
+        // a word starting exactly at `at`
+        let mut start = at.min(haystack.len()).saturating_sub(1);
+        while start > 0 && at - start < 4 && haystack[start] & 0xC0 == 0x80 {
+            start -= 1;
+        }
+        while start <= haystack.len() {
+            let captures = self.regex.captures_at(haystack, start)?;
+            let (Some(whole), Some(word)) = (captures[0].clone(), captures[1].clone()) else {
+                return None;
+            };
+            if word.start >= at {
+                return Some(word);
+            }
+            start = whole.start + haystack.get(whole.start).map_or(1, |&byte| utf8_len(byte));
+        }
+        None
+    }
+}
+
+/// Matches any of several matchers, reporting the leftmost match and the longest one among
+/// those starting at the same place.
+pub struct MultiMatcher {
+    matchers: Vec<Box<dyn Matcher + Send + Sync>>,
+}
+
+impl MultiMatcher {
+    /// Combines `matchers`; with none, nothing matches.
+    pub fn new(matchers: Vec<Box<dyn Matcher + Send + Sync>>) -> MultiMatcher {
+        MultiMatcher { matchers }
+    }
+}
+
+impl Matcher for MultiMatcher {
+    fn find_at(&self, haystack: &[u8], at: usize) -> Option<Span> {
+        self.matchers
+            .iter()
+            .filter_map(|matcher| matcher.find_at(haystack, at))
+            .min_by_key(|span| (span.start, usize::MAX - span.end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(matcher: &dyn Matcher, haystack: &str) -> Vec<Span> {
+        let mut spans = Vec::new();
+        matcher.find_iter(haystack.as_bytes(), &mut spans);
+        spans
+    }
+
+    #[test]
+    fn literal_matcher_finds_non_overlapping_matches() {
+        let matcher = LiteralMatcher::new("aba");
+
+        assert_eq!(spans(&matcher, "ababa aba"), vec![0..3, 6..9]);
+        assert_eq!(matcher.literal_prefilter(), Some(&b"aba"[..]));
+    }
+
+    #[test]
+    fn case_insensitive_matcher_folds_ascii_and_unicode() {
+        assert_eq!(
+            spans(&CaseInsensitiveMatcher::new("hello"), "HeLLo hello"),
+            vec![0..5, 6..11]
+        );
+        // The Kelvin sign folds to k
+        assert_eq!(
+            spans(&CaseInsensitiveMatcher::new("ok"), "O\u{212a}"),
+            vec![0..4]
+        );
+        assert_eq!(
+            spans(&CaseInsensitiveMatcher::new("straße"), "x STRAẞE"),
+            vec![2..10]
+        );
+    }
+
+    #[test]
+    fn word_matcher_finds_a_word_starting_at_the_search_start() {
+        let matcher = WordMatcher::new("foo", false).unwrap();
+
+        assert_eq!(matcher.find_at(b"a foo", 2), Some(2..5));
+        assert_eq!(matcher.find_at(b"foo foo", 1), Some(4..7));
+        assert_eq!(matcher.find_at("é foo".as_bytes(), 3), Some(3..6));
+        assert_eq!(matcher.find_at(b"afoo", 1), None);
+    }
+
+    #[test]
+    fn regex_matcher_reports_capture_groups() {
+        let matcher = RegexMatcher::new(r"(a)(x)?b", false).unwrap();
+
+        assert_eq!(
+            matcher.captures_at(b"zab", 0),
+            Some(vec![Some(1..3), Some(1..2), None])
+        );
+        assert_eq!(spans(&matcher, "ab axb"), vec![0..2, 3..6]);
+    }
+
+    #[test]
+    fn find_iter_skips_whole_characters_after_empty_matches() {
+        let matcher = RegexMatcher::new("x*", false).unwrap();
+
+        assert_eq!(spans(&matcher, "éx"), vec![0..0, 2..3]);
+        assert_eq!(spans(&matcher, "aé"), vec![0..0, 1..1, 3..3]);
+    }
+
+    #[test]
+    fn word_matcher_finds_adjacent_words() {
+        let matcher = WordMatcher::new("foo", false).unwrap();
+
+        assert_eq!(
+            spans(&matcher, "foo foo,foo foobar"),
+            vec![0..3, 4..7, 8..11]
+        );
+    }
+
+    #[test]
+    fn multi_matcher_prefers_the_leftmost_longest_match() {
+        let matcher = MultiMatcher::new(vec![
+            Box::new(LiteralMatcher::new("ab")),
+            Box::new(LiteralMatcher::new("abc")),
+            Box::new(LiteralMatcher::new("c")),
+        ]);
+
+        assert_eq!(spans(&matcher, "abc c"), vec![0..3, 4..5]);
+        assert!(spans(&MultiMatcher::new(Vec::new()), "abc").is_empty());
+    }
+}
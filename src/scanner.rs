@@ -1,102 +1,728 @@
-use crate::sniff::is_text_file;
-use rayon::prelude::*;
-use std::fs::File;
+use crate::color::Colors;
+use crate::error::{Error, ErrorReporter};
+use crate::json;
+use crate::matcher::Matcher;
+use crate::searcher::{Context, Match, Searcher, Sink, Span, Stats};
+use std::collections::BTreeMap;
 use std::io;
-use std::io::{BufRead, Seek};
+use std::io::{BufWriter, Read, Stdout, Write};
+use std::os::unix::ffi::OsStrExt;
 use std::path::{Path, PathBuf};
-use std::sync::Mutex;
+use std::slice;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
 
-pub fn print_matches<F>(test_match: &F, files: &[PathBuf]) -> (bool, bool)
+const MATCH_SEPARATOR: char = ':';
+const CONTEXT_SEPARATOR: char = '-';
+const GROUP_SEPARATOR: &str = "--";
+
+pub const STDIN_LABEL: &str = "(standard input)";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Print every matching line
+    #[default]
+    Lines,
+    /// Print the number of matching lines per file
+    Count,
+    /// Print the number of matches per file
+    CountMatches,
+    /// Print the paths of files with at least one match
+    FilesWithMatches,
+    /// Print the paths of files without any match
+    FilesWithoutMatch,
+}
+
+/// How the CLI prints the results of a search; context is set on the `Searcher` instead.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScanOptions {
+    pub output_mode: OutputMode,
+    pub only_matching: bool,
+    pub column: bool,
+    /// Colors to highlight the output with, `None` for plain output
+    pub colors: Option<Colors>,
+    /// Print each file's output in the order of the files list, rather than as soon as possible
+    pub ordered: bool,
+    /// Print the path once above a file's lines instead of on every line
+    pub heading: bool,
+    /// Print JSON Lines events instead of text
+    pub json: bool,
+}
+
+/// The buffered output of a file, along with the statistics of its search.
+#[derive(Default)]
+struct FileOutput {
+    text: String,
+    stats: Stats,
+}
+
+/// Searches `files` in parallel and prints their matches, returning whether any file matched.
+///
+/// Each file comes with its index, the position of its output when `options.ordered` is set.
+/// Files are received on the calling thread and searched by the thread pool as they come, so
+/// `files` may block while waiting for the next one. Once writing the output failed, the
+/// remaining files aren't searched.
+pub fn print_matches<M, I>(
+    searcher: &Searcher<M>,
+    files: I,
+    options: &ScanOptions,
+    stdout: &SharedStdout,
+    errors: &ErrorReporter,
+) -> bool
 where
-    F: Fn(&str) -> bool + Send + Sync + ?Sized,
+    M: Matcher + Sync,
+    I: IntoIterator<Item = (usize, PathBuf)>,
 {
-    let print_lock = Mutex::new(());
-    files
-        .par_iter()
-        .map(|path| scan_one_file(test_match, path.as_path(), &print_lock))
-        .reduce(
-            || (false, false),
-            |(had_match_prev, had_error_prev), (had_match_curr, had_error_curr)| {
-                (
-                    had_match_prev || had_match_curr,
-                    had_error_prev || had_error_curr,
-                )
-            },
-        )
+    let had_match = AtomicBool::new(false);
+    rayon::in_place_scope(|scope| {
+        for (index, path) in files {
+            if stdout.failed() {
+                break;
+            }
+            let had_match = &had_match;
+            scope.spawn(move |_| {
+                if stdout.failed() {
+                    return;
+                }
+                // Each file's output is buffered, so it's written in one piece under the lock
+                let mut output = FileOutput::default();
+                let label = path.display().to_string();
+                let searched = search_with_sink(&label, options, &mut output.text, None, |sink| {
+                    searcher.search_path(&path, sink)
+                });
+                if finish_search(searched, options, &mut output, errors) {
+                    had_match.store(true, Ordering::Relaxed);
+                }
+                if let Err(e) = stdout.write_file(index, output, options.ordered) {
+                    stdout.report_error(e, errors);
+                }
+            });
+        }
+    });
+
+    if let Err(e) = stdout.flush() {
+        stdout.report_error(e, errors);
+    }
+    had_match.into_inner()
 }
 
-fn sniff_text_and_rewind(file: &mut File, path: &Path) -> (bool, bool) {
-    match is_text_file(file) {
-        Err(e) => {
-            eprintln!("Error sniffing file {}. {}", path.display(), e);
-            (false, true)
-        }
-        Ok(false) => (false, false),
-        Ok(true) => match file.rewind() {
-            Err(e) => {
-                eprintln!("Error on file rewind {}. {}", path.display(), e);
-                (false, true)
-            }
-            Ok(_) => (true, false),
-        },
+/// Searches the file at `path`, the only input of the search, printing its matches as they're
+/// found.
+pub fn print_file_matches<M>(
+    searcher: &Searcher<M>,
+    path: &Path,
+    options: &ScanOptions,
+    stdout: &SharedStdout,
+    errors: &ErrorReporter,
+) -> bool
+where
+    M: Matcher,
+{
+    let label = path.display().to_string();
+    print_streamed_matches(&label, options, stdout, errors, |sink| {
+        searcher.search_path(path, sink)
+    })
+}
+
+/// Searches standard input like a file, labelling its matches with `label` and printing them as
+/// they're found.
+pub fn print_stdin_matches<M>(
+    searcher: &Searcher<M>,
+    label: &str,
+    options: &ScanOptions,
+    stdout: &SharedStdout,
+    errors: &ErrorReporter,
+) -> bool
+where
+    M: Matcher,
+{
+    let stdin = FlushBeforeRead {
+        reader: io::stdin().lock(),
+        stdout,
+        errors,
+    };
+    print_streamed_matches(label, options, stdout, errors, |sink| {
+        searcher.search_reader(Path::new(label), stdin, sink)
+    })
+}
+
+/// Flushes stdout before every read, so the lines found so far show up while waiting for more
+/// input, as with `tail -f log | rgrep ERROR`.
+struct FlushBeforeRead<'a, R> {
+    reader: R,
+    stdout: &'a SharedStdout,
+    errors: &'a ErrorReporter,
+}
+
+impl<R: Read> Read for FlushBeforeRead<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Err(e) = self.stdout.flush() {
+            self.stdout.report_error(e, self.errors);
+        }
+        self.reader.read(buf)
     }
 }
 
-fn scan_one_file<F>(test_match: &F, path: &Path, print_lock: &Mutex<()>) -> (bool, bool)
+/// Runs `search` on an input searched on its own, so its output needn't be kept together with
+/// other inputs and is written out line by line instead.
+fn print_streamed_matches<S>(
+    label: &str,
+    options: &ScanOptions,
+    stdout: &SharedStdout,
+    errors: &ErrorReporter,
+    search: S,
+) -> bool
 where
-    F: Fn(&str) -> bool + Send + Sync + ?Sized,
+    S: FnOnce(&mut dyn Sink) -> Result<Stats, Error>,
 {
-    let mut file = match File::open(path) {
-        Ok(f) => f,
+    let mut output = FileOutput::default();
+    let stream = Some((stdout, errors));
+    let searched = search_with_sink(label, options, &mut output.text, stream, search);
+    let had_match = finish_search(searched, options, &mut output, errors);
+
+    // Only the statistics are left to record, the text was written already
+    let written = stdout
+        .write_file(0, output, false)
+        .and_then(|_| stdout.flush());
+    if let Err(e) = written {
+        stdout.report_error(e, errors);
+    }
+    had_match
+}
+
+/// Runs `search` with the sink printing the output format of `options` into `output`, or
+/// straight to `stream` when set.
+fn search_with_sink<S>(
+    label: &str,
+    options: &ScanOptions,
+    output: &mut String,
+    stream: Option<(&SharedStdout, &ErrorReporter)>,
+    search: S,
+) -> Result<Stats, Error>
+where
+    S: FnOnce(&mut dyn Sink) -> Result<Stats, Error>,
+{
+    if options.json {
+        let sink = JsonSink {
+            output,
+            began: false,
+        };
+        search_streamed(sink, stream, search)
+    } else if options.output_mode != OutputMode::Lines {
+        let sink = SummarySink {
+            label,
+            options,
+            output,
+        };
+        search_streamed(sink, stream, search)
+    } else {
+        let sink = StandardSink {
+            label,
+            options,
+            output,
+            printed_heading: false,
+        };
+        search_streamed(sink, stream, search)
+    }
+}
+
+fn search_streamed<K, S>(
+    mut sink: K,
+    stream: Option<(&SharedStdout, &ErrorReporter)>,
+    search: S,
+) -> Result<Stats, Error>
+where
+    K: PrintingSink,
+    S: FnOnce(&mut dyn Sink) -> Result<Stats, Error>,
+{
+    match stream {
+        None => search(&mut sink),
+        Some((stdout, errors)) => search(&mut Streamed {
+            sink,
+            stdout,
+            errors,
+            started: false,
+        }),
+    }
+}
+
+/// A sink printing its output into a buffer.
+trait PrintingSink: Sink {
+    fn output(&mut self) -> &mut String;
+}
+
+/// Writes what `sink` prints to stdout after every line rather than once the whole input was
+/// searched, so an input as large or as slow as it gets is never held in memory.
+struct Streamed<'a, S> {
+    sink: S,
+    stdout: &'a SharedStdout,
+    errors: &'a ErrorReporter,
+    /// Whether some of the output was written already
+    started: bool,
+}
+
+impl<S: PrintingSink> Streamed<'_, S> {
+    /// Writes out what the sink printed so far, returning whether the output still works.
+    fn write(&mut self) -> bool {
+        let output = self.sink.output();
+        if !output.is_empty() {
+            let written = self.stdout.write_streamed(output, !self.started);
+            output.clear();
+            self.started = true;
+            if let Err(e) = written {
+                self.stdout.report_error(e, self.errors);
+            }
+        }
+        !self.stdout.failed()
+    }
+}
+
+impl<S: PrintingSink> Sink for Streamed<'_, S> {
+    fn matched(&mut self, found: &Match) -> bool {
+        let more = self.sink.matched(found);
+        self.write() && more
+    }
+
+    fn context(&mut self, context: &Context) -> bool {
+        let more = self.sink.context(context);
+        self.write() && more
+    }
+
+    fn context_break(&mut self) {
+        self.sink.context_break();
+        self.write();
+    }
+
+    fn finish(&mut self, path: &Path, stats: &Stats) {
+        self.sink.finish(path, stats);
+        self.write();
+    }
+}
+
+/// Reports the error of a search, if any, and returns whether the input was selected.
+fn finish_search(
+    searched: Result<Stats, Error>,
+    options: &ScanOptions,
+    output: &mut FileOutput,
+    errors: &ErrorReporter,
+) -> bool {
+    let stats = match searched {
+        Ok(stats) => stats,
         Err(e) => {
-            eprintln!("Error opening file {}. {}", path.display(), e);
-            return (false, true);
+            errors.report(e);
+            return false;
+        }
+    };
+    output.stats = stats;
+
+    match options.output_mode {
+        // Binary inputs aren't searched, so they're not listed as files without a match either
+        OutputMode::FilesWithoutMatch => stats.searches > 0 && stats.matched_lines == 0,
+        _ => stats.matched_lines > 0,
+    }
+}
+
+/// A line of text output, before it's formatted.
+struct PrintedLine<'a> {
+    number: usize,
+    separator: char,
+    column: Option<usize>,
+    text: &'a [u8],
+    spans: &'a [Span],
+}
+
+/// Formats an output line as `[label:]number:[column:]line`, highlighting its parts and spans.
+///
+/// Bytes of the line that aren't valid UTF-8 are printed as U+FFFD.
+fn format_line(label: Option<&str>, line: &PrintedLine, colors: Option<&Colors>) -> String {
+    let separator = line.separator;
+    let colors = match colors {
+        Some(colors) => colors,
+        None => {
+            let mut formatted = String::new();
+            if let Some(label) = label {
+                formatted.push_str(&format!("{}{}", label, separator));
+            }
+            formatted.push_str(&format!("{}{}", line.number, separator));
+            if let Some(column) = line.column {
+                formatted.push_str(&format!("{}{}", column, separator));
+            }
+            formatted.push_str(&String::from_utf8_lossy(line.text));
+            return formatted;
         }
     };
 
-    let (is_text, sniff_had_error) = sniff_text_and_rewind(&mut file, path);
+    let separator = colors.separator.paint(&separator.to_string());
+    let mut formatted = String::new();
+    if let Some(label) = label {
+        formatted.push_str(&colors.path.paint(label));
+        formatted.push_str(&separator);
+    }
+    formatted.push_str(&colors.line.paint(&line.number.to_string()));
+    formatted.push_str(&separator);
+    if let Some(column) = line.column {
+        formatted.push_str(&colors.column.paint(&column.to_string()));
+        formatted.push_str(&separator);
+    }
+
+    let mut written = 0;
+    for span in line.spans {
+        // Overlapping spans can't be highlighted twice
+        if span.start < written {
+            continue;
+        }
+        formatted.push_str(&String::from_utf8_lossy(&line.text[written..span.start]));
+        let matched = String::from_utf8_lossy(&line.text[span.clone()]);
+        formatted.push_str(&colors.matched.paint(&matched));
+        written = span.end;
+    }
+    formatted.push_str(&String::from_utf8_lossy(&line.text[written..]));
+
+    formatted
+}
+
+struct StdoutState {
+    writer: BufWriter<Stdout>,
+    /// Index of the next file to write when output is ordered
+    next: usize,
+    /// Output of files that finished before some earlier file
+    pending: BTreeMap<usize, FileOutput>,
+    wrote_any: bool,
+    total_stats: Stats,
+}
+
+impl StdoutState {
+    fn write_output(&mut self, output: &FileOutput, separate_files: bool) -> io::Result<()> {
+        self.total_stats.add(&output.stats);
+        if output.text.is_empty() {
+            return Ok(());
+        }
+        self.write_text(&output.text, separate_files)
+    }
+
+    /// Writes text starting the output of a file, after a blank line if `separate` is set.
+    fn write_text(&mut self, text: &str, separate: bool) -> io::Result<()> {
+        if separate && self.wrote_any {
+            self.writer.write_all(b"\n")?;
+        }
+        self.wrote_any = true;
+        self.writer.write_all(text.as_bytes())
+    }
+}
+
+/// Buffered stdout shared by the scanning threads, written to once per file so files never
+/// interleave.
+///
+/// Once a write failed, nothing more is written and the search should stop.
+pub struct SharedStdout {
+    state: Mutex<StdoutState>,
+    failed: AtomicBool,
+    /// Print a blank line between the output of two files
+    separate_files: bool,
+    /// Print a JSON summary of every search when finishing
+    json: bool,
+}
+
+impl SharedStdout {
+    pub fn new(options: &ScanOptions) -> SharedStdout {
+        SharedStdout {
+            state: Mutex::new(StdoutState {
+                writer: BufWriter::new(io::stdout()),
+                next: 0,
+                pending: BTreeMap::new(),
+                wrote_any: false,
+                total_stats: Stats::default(),
+            }),
+            failed: AtomicBool::new(false),
+            separate_files: options.heading
+                && !options.json
+                && options.output_mode == OutputMode::Lines,
+            json: options.json,
+        }
+    }
+
+    /// Returns whether writing failed, in which case nothing more should be searched.
+    pub fn failed(&self) -> bool {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    /// Reports the first write error, unless the reader of a pipe just went away, as with
+    /// `rgrep ... | head`: then the search ends quietly.
+    pub fn report_error(&self, e: io::Error, errors: &ErrorReporter) {
+        if !self.failed.swap(true, Ordering::Relaxed) && e.kind() != io::ErrorKind::BrokenPipe {
+            errors.report(Error::Output(e));
+        }
+    }
+
+    /// Writes the output of the file at `index`, after every earlier file when `ordered` is set.
+    fn write_file(&self, index: usize, output: FileOutput, ordered: bool) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.failed() {
+            return Ok(());
+        }
+        if !ordered {
+            return state.write_output(&output, self.separate_files);
+        }
 
-    if sniff_had_error {
-        return (false, true);
+        state.pending.insert(index, output);
+        loop {
+            let next = state.next;
+            let Some(output) = state.pending.remove(&next) else {
+                return Ok(());
+            };
+            state.next += 1;
+            state.write_output(&output, self.separate_files)?;
+        }
     }
-    if !is_text {
-        return (false, false);
+
+    /// Writes part of the output of an input searched on its own. `starts_input` is set for
+    /// the first part.
+    fn write_streamed(&self, text: &str, starts_input: bool) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.failed() {
+            return Ok(());
+        }
+        state.write_text(text, starts_input && self.separate_files)
     }
 
-    let mut had_match = false;
-    let mut had_error = false;
+    pub fn flush(&self) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.failed() {
+            return Ok(());
+        }
+        state.writer.flush()
+    }
+
+    /// Writes the closing summary, if the output format has one, and flushes.
+    pub fn finish(&self) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.failed() {
+            return Ok(());
+        }
+        if self.json {
+            let summary = json::summary(&state.total_stats);
+            writeln!(state.writer, "{summary}")?;
+        }
+        state.writer.flush()
+    }
+}
 
-    for line_attempt in io::BufReader::new(file).lines().enumerate() {
-        let (number, line) = match line_attempt {
-            (line_number, Ok(line)) => (line_number + 1, line),
-            (line_number, Err(e)) => {
-                eprintln!(
-                    "Error reading line {} from file {}. {}",
-                    line_number + 1,
-                    path.display(),
-                    e
-                );
-                had_error = true;
-                continue;
+/// Prints selected lines as `path:line:content`, or under a heading with the path.
+struct StandardSink<'a> {
+    label: &'a str,
+    options: &'a ScanOptions,
+    output: &'a mut String,
+    printed_heading: bool,
+}
+
+impl StandardSink<'_> {
+    fn print(&mut self, line: &PrintedLine) {
+        let colors = self.options.colors.as_ref();
+        if self.options.heading && !self.printed_heading {
+            match colors {
+                Some(colors) => self.output.push_str(&colors.path.paint(self.label)),
+                None => self.output.push_str(self.label),
             }
+            self.output.push('\n');
+            self.printed_heading = true;
+        }
+
+        let label = (!self.options.heading).then_some(self.label);
+        self.output.push_str(&format_line(label, line, colors));
+        self.output.push('\n');
+    }
+}
+
+impl PrintingSink for StandardSink<'_> {
+    fn output(&mut self) -> &mut String {
+        self.output
+    }
+}
+
+impl Sink for StandardSink<'_> {
+    fn matched(&mut self, found: &Match) -> bool {
+        if self.options.only_matching {
+            // Empty matches have nothing to show
+            for span in found.spans.iter().filter(|span| !span.is_empty()) {
+                let matched = &found.line[span.clone()];
+                let whole = 0..matched.len();
+                self.print(&PrintedLine {
+                    number: found.line_number,
+                    separator: MATCH_SEPARATOR,
+                    column: self.options.column.then_some(span.start + 1),
+                    text: matched,
+                    spans: slice::from_ref(&whole),
+                });
+            }
+            return true;
+        }
+
+        let column = found
+            .spans
+            .first()
+            .filter(|_| self.options.column)
+            .map(|span| span.start + 1);
+        self.print(&PrintedLine {
+            number: found.line_number,
+            separator: MATCH_SEPARATOR,
+            column,
+            text: found.line,
+            spans: found.spans,
+        });
+        true
+    }
+
+    fn context(&mut self, context: &Context) -> bool {
+        self.print(&PrintedLine {
+            number: context.line_number,
+            separator: CONTEXT_SEPARATOR,
+            column: None,
+            text: context.line,
+            spans: &[],
+        });
+        true
+    }
+
+    fn context_break(&mut self) {
+        match &self.options.colors {
+            Some(colors) => self
+                .output
+                .push_str(&colors.separator.paint(GROUP_SEPARATOR)),
+            None => self.output.push_str(GROUP_SEPARATOR),
+        }
+        self.output.push('\n');
+    }
+}
+
+/// Prints a count or the path of a file once it was searched, for every mode but `Lines`.
+struct SummarySink<'a> {
+    label: &'a str,
+    options: &'a ScanOptions,
+    output: &'a mut String,
+}
+
+impl PrintingSink for SummarySink<'_> {
+    fn output(&mut self) -> &mut String {
+        self.output
+    }
+}
+
+impl Sink for SummarySink<'_> {
+    fn matched(&mut self, _found: &Match) -> bool {
+        // Listing files only needs to know whether there is at least one match
+        !matches!(
+            self.options.output_mode,
+            OutputMode::FilesWithMatches | OutputMode::FilesWithoutMatch
+        )
+    }
+
+    fn finish(&mut self, _path: &Path, stats: &Stats) {
+        let (selected, summary) = match self.options.output_mode {
+            OutputMode::Count => (stats.matched_lines > 0, Some(stats.matched_lines)),
+            OutputMode::CountMatches => (stats.matches > 0, Some(stats.matches)),
+            OutputMode::FilesWithMatches => (stats.matched_lines > 0, None),
+            OutputMode::FilesWithoutMatch => (stats.matched_lines == 0, None),
+            OutputMode::Lines => unreachable!("lines are printed by StandardSink"),
         };
 
-        if test_match(&line) {
-            had_match = true;
-            let _lock = match print_lock.lock() {
-                Ok(lock) => lock,
-                Err(e) => {
-                    eprintln!(
-                        "Error acquiring lock to print line. File {}. {}",
-                        path.display(),
-                        e
-                    );
-                    return (true, true);
-                }
-            };
-            println!("{}:{}:{}", path.display(), number, line);
+        if selected {
+            print_summary(
+                self.label,
+                summary,
+                self.options.colors.as_ref(),
+                self.output,
+            );
         }
     }
+}
+
+fn print_summary(
+    label: &str,
+    summary: Option<usize>,
+    colors: Option<&Colors>,
+    output: &mut String,
+) {
+    let formatted = match (colors, summary) {
+        (Some(colors), Some(count)) => format!(
+            "{}{}{}",
+            colors.path.paint(label),
+            colors.separator.paint(&MATCH_SEPARATOR.to_string()),
+            count
+        ),
+        (Some(colors), None) => colors.path.paint(label),
+        (None, Some(count)) => format!("{label}{MATCH_SEPARATOR}{count}"),
+        (None, None) => label.to_string(),
+    };
+
+    output.push_str(&formatted);
+    output.push('\n');
+}
+
+/// Prints a `begin` event before the first line of a file, then an event for every line.
+struct JsonSink<'a> {
+    output: &'a mut String,
+    began: bool,
+}
+
+impl JsonSink<'_> {
+    fn print(
+        &mut self,
+        kind: &str,
+        path: &Path,
+        line: &[u8],
+        number: usize,
+        offset: usize,
+        spans: &[Span],
+    ) {
+        let path = path.as_os_str().as_bytes();
+        if !self.began {
+            self.output.push_str(&json::begin(path));
+            self.output.push('\n');
+            self.began = true;
+        }
+        self.output
+            .push_str(&json::line(kind, path, line, number, offset, spans));
+        self.output.push('\n');
+    }
+}
+
+impl PrintingSink for JsonSink<'_> {
+    fn output(&mut self) -> &mut String {
+        self.output
+    }
+}
+
+impl Sink for JsonSink<'_> {
+    fn matched(&mut self, found: &Match) -> bool {
+        self.print(
+            "match",
+            found.path,
+            found.line,
+            found.line_number,
+            found.byte_offset,
+            found.spans,
+        );
+        true
+    }
+
+    fn context(&mut self, context: &Context) -> bool {
+        self.print(
+            "context",
+            context.path,
+            context.line,
+            context.line_number,
+            context.byte_offset,
+            &[],
+        );
+        true
+    }
 
-    (had_match, had_error)
+    fn finish(&mut self, path: &Path, stats: &Stats) {
+        // Files without any printed line have no events at all
+        if self.began {
+            self.output
+                .push_str(&json::end(path.as_os_str().as_bytes(), stats));
+            self.output.push('\n');
+        }
+    }
 }
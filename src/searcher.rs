@@ -0,0 +1,934 @@
+use crate::error::Error;
+use crate::matcher::Matcher;
+use crate::sniff::{NUMBER_OF_BYTES_TO_SNIFF, is_text};
+use memmap2::Mmap;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::mem;
+use std::ops::Range;
+use std::path::Path;
+
+/// How many bytes are read from an input at a time.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Files of at least this many bytes are memory-mapped by `MmapChoice::Auto`.
+pub const MMAP_THRESHOLD: u64 = 1024 * 1024;
+
+/// Byte range of a match within a line.
+pub type Span = Range<usize>;
+
+/// A line selected by the matcher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<'a> {
+    pub path: &'a Path,
+    /// 1-based number of the line
+    pub line_number: usize,
+    /// Offset of the start of the line from the start of the input
+    pub byte_offset: usize,
+    /// The line, without its terminator. It may not be valid UTF-8.
+    pub line: &'a [u8],
+    /// Where the matches are in `line`; empty for lines selected by an inverted match
+    pub spans: &'a [Span],
+}
+
+/// A line around a match, requested with before or after context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Context<'a> {
+    pub path: &'a Path,
+    pub line_number: usize,
+    pub byte_offset: usize,
+    pub line: &'a [u8],
+}
+
+/// Search statistics, for a single input or summed over every input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub searches: usize,
+    pub searches_with_match: usize,
+    pub bytes_searched: usize,
+    pub matched_lines: usize,
+    pub matches: usize,
+}
+
+impl Stats {
+    pub fn add(&mut self, other: &Stats) {
+        self.searches += other.searches;
+        self.searches_with_match += other.searches_with_match;
+        self.bytes_searched += other.bytes_searched;
+        self.matched_lines += other.matched_lines;
+        self.matches += other.matches;
+    }
+}
+
+/// Whether files are memory-mapped and searched in place instead of read in chunks.
+///
+/// Inputs that can't be mapped, such as pipes or special files, are always read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MmapChoice {
+    /// Map files of at least `MMAP_THRESHOLD` bytes, for which mapping is faster than reading
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Receives the results of a `Searcher`, in the order of the input.
+pub trait Sink {
+    /// Receives a selected line. Returning `false` stops searching the current input.
+    fn matched(&mut self, found: &Match) -> bool;
+
+    /// Receives a line of context. Returning `false` stops searching the current input.
+    fn context(&mut self, _context: &Context) -> bool {
+        true
+    }
+
+    /// Called between two groups of lines that aren't adjacent, when context is requested.
+    fn context_break(&mut self) {}
+
+    /// Called once a text input was searched, even when it was stopped early or failed.
+    fn finish(&mut self, _path: &Path, _stats: &Stats) {}
+}
+
+/// Searches inputs with a `Matcher`, sending the selected lines to a `Sink`.
+pub struct Searcher<M> {
+    matcher: M,
+    invert_match: bool,
+    binary: bool,
+    before_context: usize,
+    after_context: usize,
+    mmap: MmapChoice,
+}
+
+impl<M: Matcher> Searcher<M> {
+    pub fn new(matcher: M) -> Searcher<M> {
+        Searcher {
+            matcher,
+            invert_match: false,
+            binary: false,
+            before_context: 0,
+            after_context: 0,
+            mmap: MmapChoice::Auto,
+        }
+    }
+
+    /// Selects the lines that don't match instead of those that do.
+    pub fn invert_match(mut self, invert_match: bool) -> Searcher<M> {
+        self.invert_match = invert_match;
+        self
+    }
+
+    /// Searches binary inputs as if they were text instead of skipping them.
+    pub fn binary(mut self, binary: bool) -> Searcher<M> {
+        self.binary = binary;
+        self
+    }
+
+    /// Sets how many lines of context are sent before and after each selected line.
+    pub fn with_context(mut self, before: usize, after: usize) -> Searcher<M> {
+        self.before_context = before;
+        self.after_context = after;
+        self
+    }
+
+    /// Sets when files searched with `search_path` are memory-mapped.
+    pub fn mmap(mut self, mmap: MmapChoice) -> Searcher<M> {
+        self.mmap = mmap;
+        self
+    }
+
+    /// Searches the file at `path`. Binary files are skipped and return empty stats, unless
+    /// `binary` is set.
+    pub fn search_path<S>(&self, path: &Path, sink: &mut S) -> Result<Stats, Error>
+    where
+        S: Sink + ?Sized,
+    {
+        let file = File::open(path).map_err(|e| Error::io(path, e))?;
+        match self.map(&file) {
+            Some(map) => Ok(self.search_slice(path, &map, sink)),
+            None => self.search_reader(path, file, sink),
+        }
+    }
+
+    /// Maps `file` if `self.mmap` asks for it, returning `None` to read it instead.
+    fn map(&self, file: &File) -> Option<Mmap> {
+        let min_len = match self.mmap {
+            MmapChoice::Auto => MMAP_THRESHOLD,
+            MmapChoice::Always => 1,
+            MmapChoice::Never => return None,
+        };
+        let metadata = file.metadata().ok()?;
+        if !metadata.is_file() || metadata.len() < min_len {
+            return None;
+        }
+
+        // SAFETY: the mapping is only read. If another process truncates the file while it's
+        // searched, reading the missing pages raises SIGBUS. The CLI only takes that risk for
+        // files named on the command line, or everywhere with `--mmap`; a file truncated before
+        // being mapped is read instead below.
+        let map = unsafe { Mmap::map(file) }.ok()?;
+        if map.len() as u64 != metadata.len() {
+            return None;
+        }
+        Some(map)
+    }
+
+    /// Searches `bytes` at once, reporting `path` as the path of its lines. Binary input is
+    /// skipped like in `search_path`.
+    pub fn search_slice<S>(&self, path: &Path, bytes: &[u8], sink: &mut S) -> Stats
+    where
+        S: Sink + ?Sized,
+    {
+        if !self.binary && !is_text(&bytes[..bytes.len().min(NUMBER_OF_BYTES_TO_SNIFF)]) {
+            return Stats::default();
+        }
+
+        let mut stats = Stats {
+            searches: 1,
+            ..Stats::default()
+        };
+        let chunk = Chunk { bytes, offset: 0 };
+        let mut lines = LineState::new(self);
+        stats.bytes_searched = match self.search_chunk(path, &chunk, &mut lines, sink, &mut stats) {
+            Ok(()) => bytes.len(),
+            Err(stopped_at) => stopped_at,
+        };
+        if stats.matched_lines > 0 {
+            stats.searches_with_match = 1;
+        }
+        sink.finish(path, &stats);
+
+        stats
+    }
+
+    /// Searches `reader`, reporting `path` as the path of its lines.
+    pub fn search_reader<R, S>(
+        &self,
+        path: &Path,
+        mut reader: R,
+        sink: &mut S,
+    ) -> Result<Stats, Error>
+    where
+        R: Read,
+        S: Sink + ?Sized,
+    {
+        // The sniffed prefix is replayed in front of the rest, so the input needn't be seekable.
+        // It's whatever the first read returns, so a pipe isn't waited on until it has more.
+        let mut prefix = vec![0; NUMBER_OF_BYTES_TO_SNIFF];
+        let read = loop {
+            match reader.read(&mut prefix) {
+                Ok(read) => break read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Error::io(path, e)),
+            }
+        };
+        prefix.truncate(read);
+
+        if !self.binary && !is_text(&prefix) {
+            return Ok(Stats::default());
+        }
+
+        let mut stats = Stats {
+            searches: 1,
+            ..Stats::default()
+        };
+        let searched = self.search_chunks(path, prefix, reader, sink, &mut stats);
+        if stats.matched_lines > 0 {
+            stats.searches_with_match = 1;
+        }
+        sink.finish(path, &stats);
+
+        searched.map(|_| stats)
+    }
+
+    /// Reads `reader` in chunks after the bytes already in `buffer`, and searches every chunk
+    /// once its last line is complete.
+    fn search_chunks<R, S>(
+        &self,
+        path: &Path,
+        prefix: Vec<u8>,
+        mut reader: R,
+        sink: &mut S,
+        stats: &mut Stats,
+    ) -> Result<(), Error>
+    where
+        R: Read,
+        S: Sink + ?Sized,
+    {
+        let mut lines = LineState::new(self);
+        let mut buffer = InputBuffer {
+            filled: prefix.len(),
+            bytes: prefix,
+        };
+        // Offset of the start of `buffer` from the start of the input
+        let mut buffer_offset = 0;
+        // Length of the start of `buffer` already known to hold no line terminator
+        let mut unterminated = 0;
+        // The lines of the prefix are searched before waiting for more input
+        let mut prefix_pending = buffer.filled > 0;
+
+        loop {
+            let read = if mem::take(&mut prefix_pending) {
+                buffer.filled
+            } else {
+                match buffer.read_from(&mut reader) {
+                    Ok(read) => read,
+                    Err(e) => break Err(Error::io(path, e)),
+                }
+            };
+            let filled = buffer.filled();
+            let end = if read == 0 {
+                filled.len()
+            } else {
+                match memchr::memrchr(b'\n', &filled[unterminated..]) {
+                    Some(newline) => unterminated + newline + 1,
+                    None => {
+                        // A line longer than a chunk: keep reading until it ends
+                        unterminated = filled.len();
+                        continue;
+                    }
+                }
+            };
+
+            let chunk = Chunk {
+                bytes: &filled[..end],
+                offset: buffer_offset,
+            };
+            if let Err(stopped_at) = self.search_chunk(path, &chunk, &mut lines, sink, stats) {
+                stats.bytes_searched = buffer_offset + stopped_at;
+                break Ok(());
+            }
+            stats.bytes_searched = buffer_offset + end;
+            if read == 0 {
+                break Ok(());
+            }
+            buffer.consume(end);
+            buffer_offset += end;
+            unterminated = 0;
+        }
+    }
+
+    /// Searches the complete lines of `chunk`. Returns where the search stopped if the sink
+    /// stopped it.
+    ///
+    /// The matcher runs over the whole chunk to find the next line that may match, so lines
+    /// without a match are only counted, never matched one by one.
+    fn search_chunk<S>(
+        &self,
+        path: &Path,
+        chunk: &Chunk,
+        lines: &mut LineState,
+        sink: &mut S,
+        stats: &mut Stats,
+    ) -> Result<(), usize>
+    where
+        S: Sink + ?Sized,
+    {
+        let bytes = chunk.bytes;
+        let mut position = 0;
+
+        while position < bytes.len() {
+            let candidate = self
+                .matcher
+                .find_at(bytes, position)
+                .map(|found| line_start(bytes, found.start))
+                .filter(|&start| start < bytes.len());
+
+            let skipped = position..candidate.unwrap_or(bytes.len());
+            self.skip_lines(path, chunk, skipped, lines, sink, stats)?;
+            let Some(start) = candidate else {
+                break;
+            };
+
+            let end = line_end(bytes, start);
+            lines.spans.clear();
+            self.matcher
+                .find_iter(trim_terminator(&bytes[start..end]), &mut lines.spans);
+            if !self.handle_line(path, chunk, start..end, lines, sink, stats) {
+                return Err(end);
+            }
+            position = end;
+        }
+        Ok(())
+    }
+
+    /// Handles the lines of `chunk` in `range`, which the matcher doesn't match. They're
+    /// selected by an inverted match, may be context, or are only counted.
+    ///
+    /// Returns where the search stopped if the sink stopped it.
+    fn skip_lines<S>(
+        &self,
+        path: &Path,
+        chunk: &Chunk,
+        range: Range<usize>,
+        lines: &mut LineState,
+        sink: &mut S,
+        stats: &mut Stats,
+    ) -> Result<(), usize>
+    where
+        S: Sink + ?Sized,
+    {
+        let bytes = chunk.bytes;
+        let mut start = range.start;
+
+        // Every line is selected by an inverted match, and the first ones may be after context
+        while start < range.end && (self.invert_match || lines.after_remaining > 0) {
+            let end = line_end(bytes, start);
+            lines.spans.clear();
+            if !self.handle_line(path, chunk, start..end, lines, sink, stats) {
+                return Err(end);
+            }
+            start = end;
+        }
+
+        // Only the last lines may be needed, as context before a following match
+        let mut kept = range.end;
+        for _ in 0..self.before_context {
+            if kept == start {
+                break;
+            }
+            kept = line_start(bytes, kept - 1);
+        }
+        if start < kept {
+            lines.number += count_lines(&bytes[start..kept]);
+            // The lines kept so far aren't right before the next match anymore
+            lines.before.clear();
+        }
+        while kept < range.end {
+            let end = line_end(bytes, kept);
+            lines.spans.clear();
+            self.handle_line(path, chunk, kept..end, lines, sink, stats);
+            kept = end;
+        }
+        Ok(())
+    }
+
+    /// Selects the line of `chunk` in `range` if `lines.spans` holds matches, or the opposite
+    /// for an inverted match, and sends it to the sink with its context.
+    ///
+    /// Returns `false` if the sink stopped the search.
+    fn handle_line<S>(
+        &self,
+        path: &Path,
+        chunk: &Chunk,
+        range: Range<usize>,
+        lines: &mut LineState,
+        sink: &mut S,
+        stats: &mut Stats,
+    ) -> bool
+    where
+        S: Sink + ?Sized,
+    {
+        lines.number += 1;
+        let line_number = lines.number;
+        let offset = chunk.offset + range.start;
+        let line = trim_terminator(&chunk.bytes[range]);
+
+        let selected = lines.spans.is_empty() == self.invert_match;
+        if self.invert_match {
+            // Lines selected by an inverted match have nothing to highlight
+            lines.spans.clear();
+        }
+
+        if selected {
+            stats.matched_lines += 1;
+            // Lines selected by an inverted match have no spans but still count once
+            stats.matches += lines.spans.len().max(1);
+
+            for (before_number, before_offset, before_line) in lines.before.drain(..) {
+                lines.groups.send(before_number, sink);
+                let context = Context {
+                    path,
+                    line_number: before_number,
+                    byte_offset: before_offset,
+                    line: &before_line,
+                };
+                if !sink.context(&context) {
+                    return false;
+                }
+            }
+
+            lines.groups.send(line_number, sink);
+            let found = Match {
+                path,
+                line_number,
+                byte_offset: offset,
+                line,
+                spans: &lines.spans,
+            };
+            if !sink.matched(&found) {
+                return false;
+            }
+            lines.after_remaining = self.after_context;
+        } else if lines.after_remaining > 0 {
+            lines.after_remaining -= 1;
+            lines.groups.send(line_number, sink);
+            let context = Context {
+                path,
+                line_number,
+                byte_offset: offset,
+                line,
+            };
+            if !sink.context(&context) {
+                return false;
+            }
+        } else if self.before_context > 0 {
+            if lines.before.len() == self.before_context {
+                lines.before.pop_front();
+            }
+            lines.before.push_back((line_number, offset, line.to_vec()));
+        }
+        true
+    }
+}
+
+/// Complete lines of the input, starting at `offset`.
+struct Chunk<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+/// What a search remembers from one line to the next, across chunks.
+struct LineState {
+    /// Number of the last line handled
+    number: usize,
+    /// Non-matching lines kept around in case a following line matches
+    before: VecDeque<(usize, usize, Vec<u8>)>,
+    after_remaining: usize,
+    groups: Groups,
+    /// Spans of the matches in the current line
+    spans: Vec<Span>,
+}
+
+impl LineState {
+    fn new<M>(searcher: &Searcher<M>) -> LineState {
+        LineState {
+            number: 0,
+            before: VecDeque::with_capacity(searcher.before_context),
+            after_remaining: 0,
+            groups: Groups {
+                separate: searcher.before_context > 0 || searcher.after_context > 0,
+                last_sent: None,
+            },
+            spans: Vec::new(),
+        }
+    }
+}
+
+/// Tracks the last line sent to a sink, to tell it about gaps between groups of lines.
+struct Groups {
+    /// Groups are only separated when context lines are requested
+    separate: bool,
+    last_sent: Option<usize>,
+}
+
+impl Groups {
+    fn send<S: Sink + ?Sized>(&mut self, line_number: usize, sink: &mut S) {
+        if self.separate
+            && let Some(last_sent) = self.last_sent
+            && line_number > last_sent + 1
+        {
+            sink.context_break();
+        }
+        self.last_sent = Some(line_number);
+    }
+}
+
+/// Bytes read from an input and not searched yet.
+struct InputBuffer {
+    /// Always initialized, so reading doesn't need to clear it first
+    bytes: Vec<u8>,
+    /// Length of the start of `bytes` holding input
+    filled: usize,
+}
+
+impl InputBuffer {
+    fn filled(&self) -> &[u8] {
+        &self.bytes[..self.filled]
+    }
+
+    /// Appends up to `CHUNK_SIZE` bytes from `reader`, returning how many were read.
+    fn read_from<R: Read>(&mut self, reader: &mut R) -> io::Result<usize> {
+        if self.bytes.len() - self.filled < CHUNK_SIZE {
+            self.bytes.resize(self.filled + CHUNK_SIZE, 0);
+        }
+        let space = &mut self.bytes[self.filled..self.filled + CHUNK_SIZE];
+        loop {
+            // A single read, so the complete lines already piped in are searched without
+            // waiting for a whole chunk
+            match reader.read(space) {
+                Ok(read) => {
+                    self.filled += read;
+                    return Ok(read);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Drops the first `len` bytes of input.
+    fn consume(&mut self, len: usize) {
+        self.bytes.copy_within(len..self.filled, 0);
+        self.filled -= len;
+    }
+}
+
+/// Returns where the line containing `position` starts.
+fn line_start(bytes: &[u8], position: usize) -> usize {
+    memchr::memrchr(b'\n', &bytes[..position]).map_or(0, |newline| newline + 1)
+}
+
+/// Returns where the line starting at `start` ends, after its terminator if it has one.
+fn line_end(bytes: &[u8], start: usize) -> usize {
+    memchr::memchr(b'\n', &bytes[start..]).map_or(bytes.len(), |newline| start + newline + 1)
+}
+
+/// Counts the lines in `bytes`, the last one possibly without a terminator.
+fn count_lines(bytes: &[u8]) -> usize {
+    let terminated = memchr::memchr_iter(b'\n', bytes).count();
+    match bytes.last() {
+        Some(b'\n') | None => terminated,
+        Some(_) => terminated + 1,
+    }
+}
+
+/// Strips the `\n` or `\r\n` terminator of a line.
+fn trim_terminator(line: &[u8]) -> &[u8] {
+    match line.strip_suffix(b"\n") {
+        Some(line) => line.strip_suffix(b"\r").unwrap_or(line),
+        None => line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::LiteralMatcher;
+    use std::io::Write;
+    use std::slice;
+
+    /// Records every event as a string, stopping after `limit` matches.
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+        limit: Option<usize>,
+        finished: Option<Stats>,
+    }
+
+    impl Sink for Recorder {
+        fn matched(&mut self, found: &Match) -> bool {
+            self.events.push(format!(
+                "match {}@{} {} {:?}",
+                found.line_number,
+                found.byte_offset,
+                String::from_utf8_lossy(found.line),
+                found.spans
+            ));
+            let matches = self
+                .events
+                .iter()
+                .filter(|e| e.starts_with("match"))
+                .count();
+            self.limit.is_none_or(|limit| matches < limit)
+        }
+
+        fn context(&mut self, context: &Context) -> bool {
+            self.events.push(format!(
+                "context {}@{} {}",
+                context.line_number,
+                context.byte_offset,
+                String::from_utf8_lossy(context.line)
+            ));
+            true
+        }
+
+        fn context_break(&mut self) {
+            self.events.push("--".to_string());
+        }
+
+        fn finish(&mut self, _path: &Path, stats: &Stats) {
+            self.finished = Some(*stats);
+        }
+    }
+
+    fn find_cat() -> LiteralMatcher {
+        LiteralMatcher::new("cat")
+    }
+
+    #[test]
+    fn search_reader_sends_matches_with_offsets_and_stats() {
+        let searcher = Searcher::new(find_cat());
+        let mut sink = Recorder::default();
+
+        let stats = searcher
+            .search_reader(
+                Path::new("in"),
+                "a cat\ndog\r\ncat cat\n".as_bytes(),
+                &mut sink,
+            )
+            .unwrap();
+
+        assert_eq!(
+            sink.events,
+            vec!["match 1@0 a cat [2..5]", "match 3@11 cat cat [0..3, 4..7]"]
+        );
+        let expected = Stats {
+            searches: 1,
+            searches_with_match: 1,
+            bytes_searched: 19,
+            matched_lines: 2,
+            matches: 3,
+        };
+        assert_eq!(stats, expected);
+        assert_eq!(sink.finished, Some(expected));
+    }
+
+    #[test]
+    fn search_reader_sends_context_and_breaks_between_groups() {
+        let searcher = Searcher::new(find_cat()).with_context(1, 1);
+        let mut sink = Recorder::default();
+        let input = "1\ncat\n3\n4\n5\n6\ncat\n";
+
+        searcher
+            .search_reader(Path::new("in"), input.as_bytes(), &mut sink)
+            .unwrap();
+
+        assert_eq!(
+            sink.events,
+            vec![
+                "context 1@0 1",
+                "match 2@2 cat [0..3]",
+                "context 3@6 3",
+                "--",
+                "context 6@12 6",
+                "match 7@14 cat [0..3]",
+            ]
+        );
+    }
+
+    #[test]
+    fn search_reader_inverted_match_selects_other_lines_without_spans() {
+        let searcher = Searcher::new(find_cat()).invert_match(true);
+        let mut sink = Recorder::default();
+
+        let stats = searcher
+            .search_reader(
+                Path::new("in"),
+                "cat
+dog
+"
+                .as_bytes(),
+                &mut sink,
+            )
+            .unwrap();
+
+        assert_eq!(sink.events, vec!["match 2@4 dog []"]);
+        assert_eq!(stats.matches, 1);
+    }
+
+    #[test]
+    fn search_reader_stops_when_the_sink_says_so() {
+        let searcher = Searcher::new(find_cat());
+        let mut sink = Recorder {
+            limit: Some(1),
+            ..Recorder::default()
+        };
+
+        let stats = searcher
+            .search_reader(Path::new("in"), "cat\ncat\n".as_bytes(), &mut sink)
+            .unwrap();
+
+        assert_eq!(sink.events.len(), 1);
+        assert_eq!(stats.matched_lines, 1);
+    }
+
+    #[test]
+    fn search_reader_searches_lines_that_are_not_valid_utf8() {
+        let searcher = Searcher::new(find_cat());
+        let mut sink = Recorder::default();
+
+        let stats = searcher
+            .search_reader(Path::new("in"), &b"\xe9 cat\n\xff\n"[..], &mut sink)
+            .unwrap();
+
+        assert_eq!(sink.events, vec!["match 1@0 \u{fffd} cat [2..5]"]);
+        assert_eq!(stats.bytes_searched, 8);
+    }
+
+    /// Gives out at most `size` bytes per read, like a slow pipe.
+    struct Trickle<'a> {
+        input: &'a [u8],
+        size: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let read = self.size.min(buf.len()).min(self.input.len());
+            buf[..read].copy_from_slice(&self.input[..read]);
+            self.input = &self.input[read..];
+            Ok(read)
+        }
+    }
+
+    /// Gives out `input` in a single read, then fails like a broken pipe.
+    struct FailsAfterFirstRead<'a> {
+        input: Option<&'a [u8]>,
+    }
+
+    impl Read for FailsAfterFirstRead<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Some(input) = self.input.take() else {
+                return Err(io::ErrorKind::BrokenPipe.into());
+            };
+            buf[..input.len()].copy_from_slice(input);
+            Ok(input.len())
+        }
+    }
+
+    #[test]
+    fn search_reader_searches_lines_of_the_first_read_before_reading_more() {
+        let searcher = Searcher::new(find_cat());
+        let mut sink = Recorder::default();
+        let reader = FailsAfterFirstRead {
+            input: Some(b"a cat\n"),
+        };
+
+        let searched = searcher.search_reader(Path::new("in"), reader, &mut sink);
+
+        assert!(searched.is_err());
+        assert_eq!(sink.events, vec!["match 1@0 a cat [2..5]"]);
+    }
+
+    #[test]
+    fn search_reader_finds_lines_across_chunks() {
+        let mut input = String::new();
+        for number in 1..=20_000 {
+            if number % 997 == 0 {
+                input.push_str("a cat\n");
+            } else {
+                input.push_str(&format!("line {number}\n"));
+            }
+        }
+        // A line longer than a chunk
+        input.push_str(&"x".repeat(CHUNK_SIZE * 2));
+        input.push_str("cat");
+
+        let mut expected = Recorder::default();
+        let mut offset = 0;
+        for (index, line) in input.split('\n').enumerate() {
+            let found = Match {
+                path: Path::new("in"),
+                line_number: index + 1,
+                byte_offset: offset,
+                line: line.as_bytes(),
+                spans: &[],
+            };
+            if let Some(start) = line.find("cat") {
+                expected.matched(&Match {
+                    spans: slice::from_ref(&(start..start + 3)),
+                    ..found
+                });
+            }
+            offset += line.len() + 1;
+        }
+
+        for size in [7, CHUNK_SIZE] {
+            let searcher = Searcher::new(find_cat());
+            let mut sink = Recorder::default();
+            let reader = Trickle {
+                input: input.as_bytes(),
+                size,
+            };
+
+            let stats = searcher
+                .search_reader(Path::new("in"), reader, &mut sink)
+                .unwrap();
+
+            assert_eq!(sink.events, expected.events);
+            assert_eq!(stats.bytes_searched, input.len());
+        }
+    }
+
+    #[test]
+    fn search_reader_only_keeps_lines_right_before_a_match_as_context() {
+        let searcher = Searcher::new(find_cat()).with_context(2, 0);
+        let mut sink = Recorder::default();
+        let input = "1\n2\ncat\n4\n5\n6\n7\ncat\n";
+
+        searcher
+            .search_reader(Path::new("in"), input.as_bytes(), &mut sink)
+            .unwrap();
+
+        assert_eq!(
+            sink.events,
+            vec![
+                "context 1@0 1",
+                "context 2@2 2",
+                "match 3@4 cat [0..3]",
+                "--",
+                "context 6@12 6",
+                "context 7@14 7",
+                "match 8@16 cat [0..3]",
+            ]
+        );
+    }
+
+    #[test]
+    fn search_reader_skips_binary_input() {
+        let searcher = Searcher::new(find_cat());
+        let mut sink = Recorder::default();
+
+        let stats = searcher
+            .search_reader(Path::new("in"), "cat\0\n".as_bytes(), &mut sink)
+            .unwrap();
+
+        assert!(sink.events.is_empty());
+        assert_eq!(sink.finished, None);
+        assert_eq!(stats, Stats::default());
+    }
+
+    #[test]
+    fn search_path_finds_the_same_lines_with_or_without_mmap() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"1\ncat\n3\n4\n5\ncat\r\nlast cat").unwrap();
+
+        let mut events = Vec::new();
+        for mmap in [MmapChoice::Always, MmapChoice::Never] {
+            let searcher = Searcher::new(find_cat()).with_context(1, 1).mmap(mmap);
+            let mut sink = Recorder::default();
+
+            let stats = searcher.search_path(file.path(), &mut sink).unwrap();
+
+            assert_eq!(stats.bytes_searched, 25);
+            assert_eq!(sink.finished, Some(stats));
+            events.push(sink.events);
+        }
+        assert_eq!(events[0], events[1]);
+        assert_eq!(
+            events[0],
+            vec![
+                "context 1@0 1",
+                "match 2@2 cat [0..3]",
+                "context 3@6 3",
+                "--",
+                "context 5@10 5",
+                "match 6@12 cat [0..3]",
+                "match 7@17 last cat [5..8]",
+            ]
+        );
+    }
+
+    #[test]
+    fn search_path_reports_missing_files() {
+        let searcher = Searcher::new(find_cat());
+        let mut sink = Recorder::default();
+
+        let error = searcher
+            .search_path(Path::new("does/not/exist"), &mut sink)
+            .unwrap_err();
+
+        assert_eq!(error.kind(), crate::error::ErrorKind::NotFound);
+    }
+}
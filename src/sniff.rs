@@ -1,7 +1,7 @@
+use std::io;
 use std::io::Read;
-use std::{io, str};
 
-const NUMBER_OF_BYTES_TO_SNIFF: usize = 4096;
+pub const NUMBER_OF_BYTES_TO_SNIFF: usize = 4096;
 const NUL_BYTE: u8 = b'\x00';
 
 pub fn is_text_file(file: &mut impl Read) -> io::Result<bool> {
@@ -17,16 +17,14 @@ pub fn is_text_file(file: &mut impl Read) -> io::Result<bool> {
     }
 
     // Take the relevant slice of bytes that were read from the file
-    let relevant_bytes = &buf[..bytes_read];
-
-    // Check for NUL byte or try converting to str
-    if relevant_bytes.iter().any(|byte| byte == &NUL_BYTE)
-        || str::from_utf8(relevant_bytes).is_err()
-    {
-        return Ok(false);
-    }
+    Ok(is_text(&buf[..bytes_read]))
+}
 
-    Ok(true)
+/// Returns whether `bytes`, sampled from the start of a file, look like text.
+pub fn is_text(bytes: &[u8]) -> bool {
+    // Only a NUL byte makes a file binary: text in another encoding than UTF-8, such as
+    // Latin-1, is still searched
+    !bytes.contains(&NUL_BYTE)
 }
 
 #[cfg(test)]
@@ -78,7 +76,7 @@ mod tests {
     }
 
     #[test]
-    fn is_text_file_invalid_utf8_returns_false() {
+    fn is_text_file_invalid_utf8_returns_true() {
         let mut temp_file = NamedTempFile::new().unwrap();
 
         // File with invalid utf-8 0xFF
@@ -86,11 +84,11 @@ mod tests {
 
         temp_file.rewind().unwrap();
 
-        assert!(!is_text_file(temp_file.as_file_mut()).unwrap());
+        assert!(is_text_file(temp_file.as_file_mut()).unwrap());
     }
 
     #[test]
-    fn is_text_file_contains_lone_continuation_byte_returns_false() {
+    fn is_text_file_contains_lone_continuation_byte_returns_true() {
         let mut temp_file = NamedTempFile::new().unwrap();
 
         // File with lone continuation byte 0x80
@@ -98,7 +96,7 @@ mod tests {
 
         temp_file.rewind().unwrap();
 
-        assert!(!is_text_file(temp_file.as_file_mut()).unwrap());
+        assert!(is_text_file(temp_file.as_file_mut()).unwrap());
     }
 
     #[test]
@@ -1,67 +1,457 @@
+use crate::error::{Error, ErrorReporter};
+use crate::ignore::{Gitignore, Globs, IGNORE_FILE_NAMES, Match};
+use rayon::{Scope, ThreadPool, ThreadPoolBuilder};
 use std::ffi::OsStr;
-use std::io;
+use std::fs;
+use std::fs::{DirEntry, Metadata};
+use std::os::unix::fs::DirEntryExt;
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Don't respect ignore files, nor skip `.git` directories
+    pub no_ignore: bool,
+    /// Walk hidden files and directories too
+    pub hidden: bool,
+    /// Follow symbolic links to files and directories
+    pub follow: bool,
+    /// Include and exclude globs, matched against paths relative to each root
+    pub globs: Globs,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortBy {
+    /// Keep the walk order and print results as soon as they're found
+    #[default]
+    None,
+    Path,
+    Modified,
+    Created,
+}
+
+impl SortBy {
+    pub fn parse(value: &str) -> Result<SortBy, String> {
+        match value {
+            "none" => Ok(SortBy::None),
+            "path" => Ok(SortBy::Path),
+            "modified" => Ok(SortBy::Modified),
+            "created" => Ok(SortBy::Created),
+            _ => Err(format!(
+                "invalid sort '{value}': expected path, modified, created or none"
+            )),
+        }
+    }
+}
+
+/// Sorts `files` in ascending order of `sort`. Files whose time can't be read go first.
+pub fn sort_files(files: &mut [PathBuf], sort: SortBy) {
+    let time = |path: &PathBuf, created: bool| -> Option<SystemTime> {
+        let metadata = path.metadata().ok()?;
+        if created {
+            metadata.created().ok()
+        } else {
+            metadata.modified().ok()
+        }
+    };
+
+    match sort {
+        SortBy::None => {}
+        SortBy::Path => files.sort(),
+        SortBy::Modified => files.sort_by_cached_key(|path| (time(path, false), path.clone())),
+        SortBy::Created => files.sort_by_cached_key(|path| (time(path, true), path.clone())),
+    }
+}
 
 fn is_hidden(file_name: &OsStr) -> bool {
     file_name.to_string_lossy().starts_with('.')
 }
 
-pub fn collect_files(root: &Path) -> io::Result<Vec<PathBuf>> {
-    let mut files_result: Vec<PathBuf> = Vec::new();
+fn read_ignore_files(dir: &Path, errors: &ErrorReporter) -> Vec<Gitignore> {
+    let mut ignore_paths = vec![dir.join(".git").join("info").join("exclude")];
+    ignore_paths.extend(IGNORE_FILE_NAMES.iter().map(|name| dir.join(name)));
 
-    if root.is_file() {
-        files_result.push(root.to_path_buf());
-    } else if root.is_dir() {
-        let root_read_dir = root.read_dir();
+    let mut ignores = Vec::new();
+    for ignore_path in ignore_paths {
+        if !ignore_path.is_file() {
+            continue;
+        }
+        match Gitignore::from_file(dir, &ignore_path) {
+            Ok(ignore) if !ignore.is_empty() => ignores.push(ignore),
+            Ok(_) => {}
+            Err(e) => errors.report(Error::io(ignore_path, e)),
+        }
+    }
+    ignores
+}
 
-        let iter = match root_read_dir {
-            Ok(iter) => iter,
-            Err(e) => {
-                eprintln!("Error reading dir: {}", e);
-                return Ok(files_result);
+/// Reads the ignore files of the directories above `root`, up to the root of the repository it's
+/// in, so that they apply below `root` too. Outside a repository, none are read.
+fn read_parent_ignores(root: &Path, errors: &ErrorReporter) -> Option<Arc<IgnoreStack>> {
+    let resolved = root.canonicalize().ok()?;
+    if resolved.join(".git").exists() {
+        return None;
+    }
+    let parents: Vec<&Path> = resolved.ancestors().skip(1).collect();
+    let repository = parents.iter().position(|dir| dir.join(".git").exists())?;
+
+    // From the repository root down, so that nearer ignore files take precedence
+    let mut stack = None;
+    for dir in parents[..=repository].iter().rev() {
+        let base = resolved
+            .strip_prefix(dir)
+            .expect("a directory is below its ancestors");
+        let ignores: Vec<Gitignore> = read_ignore_files(dir, errors)
+            .into_iter()
+            .map(|ignore| ignore.rebase(root, base))
+            .collect();
+        if !ignores.is_empty() {
+            stack = Some(Arc::new(IgnoreStack {
+                ignores,
+                parent: stack,
+            }));
+        }
+    }
+    stack
+}
+
+/// The ignore files of a directory and of its ancestors. Subdirectories are walked in parallel,
+/// so each one shares its parent's stack and only adds its own files on top.
+struct IgnoreStack {
+    ignores: Vec<Gitignore>,
+    parent: Option<Arc<IgnoreStack>>,
+}
+
+/// A directory being walked and its ancestors, to detect symbolic links leading back to one of
+/// them. Only tracked when following links, as the tree can't loop otherwise.
+struct Ancestor {
+    /// Device and inode numbers of the directory
+    id: (u64, u64),
+    path: PathBuf,
+    parent: Option<Arc<Ancestor>>,
+}
+
+impl Ancestor {
+    fn new(path: &Path, metadata: &Metadata, parent: Option<Arc<Ancestor>>) -> Arc<Ancestor> {
+        Arc::new(Ancestor {
+            id: (metadata.dev(), metadata.ino()),
+            path: path.to_path_buf(),
+            parent,
+        })
+    }
+}
+
+/// Returns the ancestor in `ancestors` that is the same directory as `metadata`, if any.
+fn find_ancestor<'a>(ancestors: Option<&'a Ancestor>, metadata: &Metadata) -> Option<&'a Ancestor> {
+    let id = (metadata.dev(), metadata.ino());
+    let mut ancestor = ancestors;
+    while let Some(current) = ancestor {
+        if current.id == id {
+            return Some(current);
+        }
+        ancestor = current.parent.as_deref();
+    }
+    None
+}
+
+fn is_ignored(ignores: Option<&IgnoreStack>, path: &Path, is_dir: bool) -> bool {
+    // Deeper ignore files take precedence over the ones from their parents
+    let mut stack = ignores;
+    while let Some(current) = stack {
+        for ignore in current.ignores.iter().rev() {
+            match ignore.matched(path, is_dir) {
+                Match::Ignore => return true,
+                Match::Whitelist => return false,
+                Match::None => {}
             }
-        };
+        }
+        stack = current.parent.as_deref();
+    }
+    false
+}
 
-        for entry in iter {
-            let child_entry = match entry {
-                Ok(entry) => entry,
-                Err(e) => {
-                    eprintln!("Error reading file: {}", e);
-                    continue;
-                }
+/// Drops repeated roots, keeping the first spelling seen. Roots nested inside another root are
+/// kept, and walked on their own by `walk_parallel`.
+pub fn dedup_roots(roots: &[PathBuf]) -> Vec<PathBuf> {
+    let mut seen: Vec<PathBuf> = Vec::new();
+    let mut result = Vec::new();
+
+    for root in roots {
+        // Roots that can't be resolved are kept as-is so that walking them reports the error
+        let resolved = root.canonicalize().unwrap_or_else(|_| root.clone());
+        if !seen.contains(&resolved) {
+            seen.push(resolved);
+            result.push(root.clone());
+        }
+    }
+
+    result
+}
+
+/// Returns the device and inode numbers of the roots lying inside another root.
+fn nested_root_ids(roots: &[PathBuf]) -> Vec<(u64, u64)> {
+    let resolved: Vec<Option<PathBuf>> =
+        roots.iter().map(|root| root.canonicalize().ok()).collect();
+
+    roots
+        .iter()
+        .zip(&resolved)
+        .filter_map(|(root, path)| {
+            let path = path.as_ref()?;
+            let nested = resolved
+                .iter()
+                .flatten()
+                .any(|other| other != path && path.starts_with(other));
+            if !nested {
+                return None;
+            }
+            let metadata = fs::metadata(root).ok()?;
+            Some((metadata.dev(), metadata.ino()))
+        })
+        .collect()
+}
+
+/// Returns whether `entry` is one of `nested_roots`, going by what it points to if `followed`.
+fn is_nested_root(
+    entry: &DirEntry,
+    followed: Option<&Metadata>,
+    nested_roots: &[(u64, u64)],
+) -> bool {
+    let id = match followed {
+        Some(metadata) => (metadata.dev(), metadata.ino()),
+        // The inode number comes with the entry, so only a likely match costs a stat
+        None if nested_roots.iter().any(|&(_, ino)| ino == entry.ino()) => match entry.metadata() {
+            Ok(metadata) => (metadata.dev(), metadata.ino()),
+            Err(_) => return false,
+        },
+        None => return false,
+    };
+    nested_roots.contains(&id)
+}
+
+/// What every directory walk shares.
+#[derive(Clone, Copy)]
+struct Walk<'a> {
+    /// The root being walked, which globs are relative to
+    root: &'a Path,
+    /// Roots inside other roots: they're walked on their own, with their own filters, so other
+    /// walks skip them
+    nested_roots: &'a [(u64, u64)],
+    options: &'a WalkOptions,
+    errors: &'a ErrorReporter,
+    files: &'a Sender<PathBuf>,
+}
+
+/// Walks `roots` in parallel and sends every file to `files` as soon as it's found, so files can
+/// be searched while the walk goes on. Errors, including missing roots, are reported to `errors`.
+///
+/// A root inside another root is always searched, like any explicit root: only what's below it
+/// is filtered. The walk of the enclosing root skips it, so its files are only sent once.
+///
+/// The walk runs on a thread pool of its own: if it shared the pool of the threads searching the
+/// files, they could all be blocked waiting for files that no thread is left to find.
+pub fn walk_parallel(
+    roots: &[PathBuf],
+    options: &WalkOptions,
+    errors: &ErrorReporter,
+    files: &Sender<PathBuf>,
+) {
+    let nested_roots = nested_root_ids(roots);
+    walker_pool().scope(|scope| {
+        for root in roots {
+            let walk = Walk {
+                root,
+                nested_roots: &nested_roots,
+                options,
+                errors,
+                files,
             };
+            if let Err(e) = walk_root(scope, walk) {
+                errors.report(e);
+            }
+        }
+    });
+}
 
-            let child_file_type = match child_entry.file_type() {
-                Ok(file_type) => file_type,
-                Err(e) => {
-                    eprintln!("Error reading file type: {}", e);
-                    continue;
-                }
+fn walker_pool() -> ThreadPool {
+    ThreadPoolBuilder::new()
+        .thread_name(|index| format!("rgrep-walker-{index}"))
+        .build()
+        .expect("failed to start the directory walker threads")
+}
+
+fn walk_root<'scope>(scope: &Scope<'scope>, walk: Walk<'scope>) -> Result<(), Error> {
+    let root = walk.root;
+    // Surfaces missing roots and permission errors instead of silently finding nothing
+    let metadata = root.metadata().map_err(|e| Error::io(root, e))?;
+
+    if metadata.is_file() {
+        // The receiver is only gone once the search stopped, so nothing is lost
+        let _ = walk.files.send(root.to_path_buf());
+    } else if metadata.is_dir() {
+        let ancestors = walk
+            .options
+            .follow
+            .then(|| Ancestor::new(root, &metadata, None));
+        let root = root.to_path_buf();
+        scope.spawn(move |scope| {
+            let ignores = if walk.options.no_ignore {
+                None
+            } else {
+                read_parent_ignores(&root, walk.errors)
             };
+            walk_dir(scope, &root, ignores, ancestors, walk)
+        });
+    }
+    Ok(())
+}
+
+/// Sends the files of `dir` and spawns a walk of each of its subdirectories.
+fn walk_dir<'scope>(
+    scope: &Scope<'scope>,
+    dir: &Path,
+    parent_ignores: Option<Arc<IgnoreStack>>,
+    ancestors: Option<Arc<Ancestor>>,
+    walk: Walk<'scope>,
+) {
+    let iter = match dir.read_dir() {
+        Ok(iter) => iter,
+        Err(e) => {
+            walk.errors.report(Error::io(dir, e));
+            return;
+        }
+    };
+
+    let own_ignores = if walk.options.no_ignore {
+        Vec::new()
+    } else {
+        read_ignore_files(dir, walk.errors)
+    };
+    let ignores = if own_ignores.is_empty() {
+        parent_ignores
+    } else {
+        Some(Arc::new(IgnoreStack {
+            ignores: own_ignores,
+            parent: parent_ignores,
+        }))
+    };
 
-            if is_hidden(&child_entry.file_name()) {
+    for entry in iter {
+        let child_entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                walk.errors.report(Error::io(dir, e));
                 continue;
             }
+        };
 
-            let child_path = child_entry.path();
-            if child_file_type.is_file() {
-                files_result.push(child_path);
-            } else if child_file_type.is_dir() {
-                files_result.extend(collect_files(&child_path)?);
-            } else {
-                // Not printing on purpose for now, to avoid spamming
-                // println!("{:?} is not a file or directory", child_path);
+        let child_path = child_entry.path();
+        let child_file_type = match child_entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(e) => {
+                walk.errors.report(Error::io(child_path, e));
+                continue;
             }
+        };
+
+        // Names alone decide these, so links they skip are never followed
+        let file_name = child_entry.file_name();
+        if !walk.options.hidden && is_hidden(&file_name) {
+            continue;
         }
-    }
+        // Repository internals are skipped like an ignored directory, even when hidden
+        // files are walked
+        if !walk.options.no_ignore && file_name == ".git" {
+            continue;
+        }
+
+        // Following links needs what they point to, and the identity of every directory to
+        // detect loops
+        let followed = (walk.options.follow
+            && (child_file_type.is_symlink() || child_file_type.is_dir()))
+        .then(|| fs::metadata(&child_path));
+        let followed_metadata = followed
+            .as_ref()
+            .and_then(|followed| followed.as_ref().ok());
+        if !walk.nested_roots.is_empty()
+            && is_nested_root(&child_entry, followed_metadata, walk.nested_roots)
+        {
+            continue;
+        }
+        // A broken link is filtered like a file
+        let child_file_type = followed_metadata.map_or(child_file_type, Metadata::file_type);
+
+        if is_ignored(ignores.as_deref(), &child_path, child_file_type.is_dir()) {
+            continue;
+        }
+        if let Ok(relative) = child_path.strip_prefix(walk.root)
+            && walk
+                .options
+                .globs
+                .is_excluded(relative, child_file_type.is_dir())
+        {
+            continue;
+        }
+        // Only reported now, so links that would be skipped anyway don't fail the search
+        let followed = match followed {
+            Some(Ok(metadata)) => Some(metadata),
+            Some(Err(e)) => {
+                walk.errors.report(Error::io(child_path, e));
+                continue;
+            }
+            None => None,
+        };
 
-    Ok(files_result)
+        if child_file_type.is_file() {
+            let _ = walk.files.send(child_path);
+        } else if child_file_type.is_dir() {
+            let child_ancestors = match &followed {
+                Some(metadata) => {
+                    if let Some(ancestor) = find_ancestor(ancestors.as_deref(), metadata) {
+                        walk.errors.report(Error::Loop {
+                            path: child_path,
+                            ancestor: ancestor.path.clone(),
+                        });
+                        continue;
+                    }
+                    Some(Ancestor::new(&child_path, metadata, ancestors.clone()))
+                }
+                None => None,
+            };
+            let ignores = ignores.clone();
+            scope.spawn(move |scope| walk_dir(scope, &child_path, ignores, child_ancestors, walk));
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::{File, create_dir};
+    use std::fs::{File, create_dir, create_dir_all, write};
+    use std::sync::mpsc;
+
+    /// Collects the files under `root` alone. Errors below the root are reported to `errors`.
+    fn collect_files(
+        root: &Path,
+        options: &WalkOptions,
+        errors: &ErrorReporter,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let (files, found) = mpsc::channel();
+        let walk = Walk {
+            root,
+            nested_roots: &[],
+            options,
+            errors,
+            files: &files,
+        };
+        walker_pool().scope(|scope| walk_root(scope, walk))?;
+
+        drop(files);
+        Ok(found.into_iter().collect())
+    }
 
     #[test]
     fn is_hidden_dotfile_returns_true() {
@@ -121,7 +511,12 @@ mod tests {
         File::create(root_path.join(".secret")).unwrap();
 
         // When passing "root"
-        let collected_paths = collect_files(root_path).unwrap();
+        let collected_paths = collect_files(
+            root_path,
+            &WalkOptions::default(),
+            &ErrorReporter::default(),
+        )
+        .unwrap();
 
         assert_eq!(collected_paths.len(), 1);
 
@@ -153,16 +548,58 @@ mod tests {
         File::create(root_path.join("b.txt")).unwrap();
 
         // When passing "root"
-        let collected_paths = collect_files(root_path).unwrap();
+        let collected_paths = collect_files(
+            root_path,
+            &WalkOptions::default(),
+            &ErrorReporter::default(),
+        )
+        .unwrap();
 
         assert_eq!(collected_paths.len(), 1);
 
         let result = collected_paths.first().unwrap();
-        let expect = &PathBuf::from(root_path.join("b.txt"));
+        let expect = &root_path.join("b.txt");
 
         assert_eq!(result, expect);
     }
 
+    #[test]
+    fn collect_files_hidden_includes_dotfiles_but_not_git() {
+        // File structure:
+        // root/
+        // |- .git/
+        //  |- config
+        // |- .config/
+        //  |- settings.toml
+        // |- .env
+
+        // Create root folder
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path();
+
+        create_dir(root_path.join(".git")).unwrap();
+        File::create(root_path.join(".git").join("config")).unwrap();
+        create_dir(root_path.join(".config")).unwrap();
+        File::create(root_path.join(".config").join("settings.toml")).unwrap();
+        File::create(root_path.join(".env")).unwrap();
+
+        // When passing "root" with --hidden
+        let options = WalkOptions {
+            hidden: true,
+            ..WalkOptions::default()
+        };
+        let collected_paths =
+            collect_files(root_path, &options, &ErrorReporter::default()).unwrap();
+
+        assert_eq!(
+            sorted_relative(collected_paths, root_path),
+            vec![
+                PathBuf::from(".config/settings.toml"),
+                PathBuf::from(".env"),
+            ]
+        );
+    }
+
     #[test]
     fn collect_files_allows_explicit_hidden_file_root() {
         // File structure:
@@ -178,7 +615,12 @@ mod tests {
         File::create(env_path.as_path()).unwrap();
 
         // When passing "root/.env"
-        let collected_paths = collect_files(env_path.as_path()).unwrap();
+        let collected_paths = collect_files(
+            env_path.as_path(),
+            &WalkOptions::default(),
+            &ErrorReporter::default(),
+        )
+        .unwrap();
 
         assert_eq!(collected_paths.len(), 1);
 
@@ -207,7 +649,12 @@ mod tests {
         File::create(&git_config_path).unwrap();
 
         // When passing "root/.git/"
-        let collected_paths = collect_files(dir_git_path.as_path()).unwrap();
+        let collected_paths = collect_files(
+            dir_git_path.as_path(),
+            &WalkOptions::default(),
+            &ErrorReporter::default(),
+        )
+        .unwrap();
 
         assert_eq!(collected_paths.len(), 1);
 
@@ -241,8 +688,573 @@ mod tests {
         File::create(&visible_file_path).unwrap();
 
         // When passing "root"
-        let collected_paths = collect_files(root_path).unwrap();
+        let collected_paths = collect_files(
+            root_path,
+            &WalkOptions::default(),
+            &ErrorReporter::default(),
+        )
+        .unwrap();
 
         assert_eq!(collected_paths.len(), 0);
     }
+
+    fn sorted_relative(paths: Vec<PathBuf>, root: &Path) -> Vec<PathBuf> {
+        let mut relative: Vec<PathBuf> = paths
+            .iter()
+            .map(|path| path.strip_prefix(root).unwrap().to_path_buf())
+            .collect();
+        relative.sort();
+        relative
+    }
+
+    #[test]
+    fn collect_files_skips_gitignored_entries() {
+        // File structure:
+        // root/
+        // |- .gitignore ("target/\n*.log\n")
+        // |- target/
+        //  |- out.txt
+        // |- debug.log
+        // |- main.rs
+
+        // Create root folder
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path();
+
+        write(root_path.join(".gitignore"), "target/\n*.log\n").unwrap();
+        create_dir(root_path.join("target")).unwrap();
+        File::create(root_path.join("target").join("out.txt")).unwrap();
+        File::create(root_path.join("debug.log")).unwrap();
+        File::create(root_path.join("main.rs")).unwrap();
+
+        // When passing "root"
+        let collected_paths = collect_files(
+            root_path,
+            &WalkOptions::default(),
+            &ErrorReporter::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            sorted_relative(collected_paths, root_path),
+            vec![PathBuf::from("main.rs")]
+        );
+    }
+
+    #[test]
+    fn collect_files_applies_nested_ignore_files_hierarchically() {
+        // File structure:
+        // root/
+        // |- .gitignore ("*.log\n")
+        // |- sub/
+        //  |- .gitignore ("!keep.log\n/local.txt\n")
+        //  |- keep.log
+        //  |- drop.log
+        //  |- local.txt
+        //  |- deeper/
+        //   |- local.txt
+
+        // Create root folder
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path();
+
+        write(root_path.join(".gitignore"), "*.log\n").unwrap();
+        let sub_path = root_path.join("sub");
+        create_dir(&sub_path).unwrap();
+        write(sub_path.join(".gitignore"), "!keep.log\n/local.txt\n").unwrap();
+        File::create(sub_path.join("keep.log")).unwrap();
+        File::create(sub_path.join("drop.log")).unwrap();
+        File::create(sub_path.join("local.txt")).unwrap();
+        create_dir(sub_path.join("deeper")).unwrap();
+        File::create(sub_path.join("deeper").join("local.txt")).unwrap();
+
+        // When passing "root"
+        let collected_paths = collect_files(
+            root_path,
+            &WalkOptions::default(),
+            &ErrorReporter::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            sorted_relative(collected_paths, root_path),
+            vec![
+                PathBuf::from("sub/deeper/local.txt"),
+                PathBuf::from("sub/keep.log"),
+            ]
+        );
+    }
+
+    #[test]
+    fn collect_files_dot_ignore_overrides_gitignore() {
+        // File structure:
+        // root/
+        // |- .gitignore ("*.txt\n")
+        // |- .ignore ("!notes.txt\n")
+        // |- notes.txt
+        // |- other.txt
+
+        // Create root folder
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path();
+
+        write(root_path.join(".gitignore"), "*.txt\n").unwrap();
+        write(root_path.join(".ignore"), "!notes.txt\n").unwrap();
+        File::create(root_path.join("notes.txt")).unwrap();
+        File::create(root_path.join("other.txt")).unwrap();
+
+        // When passing "root"
+        let collected_paths = collect_files(
+            root_path,
+            &WalkOptions::default(),
+            &ErrorReporter::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            sorted_relative(collected_paths, root_path),
+            vec![PathBuf::from("notes.txt")]
+        );
+    }
+
+    #[test]
+    fn collect_files_honors_git_info_exclude() {
+        // File structure:
+        // root/
+        // |- .git/
+        //  |- info/
+        //   |- exclude ("secret.txt\n")
+        // |- secret.txt
+        // |- public.txt
+
+        // Create root folder
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path();
+
+        let info_path = root_path.join(".git").join("info");
+        create_dir_all(&info_path).unwrap();
+        write(info_path.join("exclude"), "secret.txt\n").unwrap();
+        File::create(root_path.join("secret.txt")).unwrap();
+        File::create(root_path.join("public.txt")).unwrap();
+
+        // When passing "root"
+        let collected_paths = collect_files(
+            root_path,
+            &WalkOptions::default(),
+            &ErrorReporter::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            sorted_relative(collected_paths, root_path),
+            vec![PathBuf::from("public.txt")]
+        );
+    }
+
+    #[test]
+    fn collect_files_no_ignore_includes_ignored_entries() {
+        // File structure:
+        // root/
+        // |- .gitignore ("*.log\n")
+        // |- debug.log
+
+        // Create root folder
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path();
+
+        write(root_path.join(".gitignore"), "*.log\n").unwrap();
+        File::create(root_path.join("debug.log")).unwrap();
+
+        // When passing "root" with --no-ignore
+        let options = WalkOptions {
+            no_ignore: true,
+            ..WalkOptions::default()
+        };
+        let collected_paths =
+            collect_files(root_path, &options, &ErrorReporter::default()).unwrap();
+
+        assert_eq!(
+            sorted_relative(collected_paths, root_path),
+            vec![PathBuf::from("debug.log")]
+        );
+    }
+
+    #[test]
+    fn walk_parallel_sends_the_files_of_every_root() {
+        // File structure:
+        // root/
+        // |- .git/
+        // |- .gitignore ("*.log\n")
+        // |- a/
+        //  |- b/
+        //   |- c/
+        //    |- deep.txt
+        //    |- deep.log
+        // |- top.txt
+
+        // Create root folder
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path();
+
+        create_dir(root_path.join(".git")).unwrap();
+        write(root_path.join(".gitignore"), "*.log\n").unwrap();
+        let deep_path = root_path.join("a").join("b").join("c");
+        create_dir_all(&deep_path).unwrap();
+        File::create(deep_path.join("deep.txt")).unwrap();
+        File::create(deep_path.join("deep.log")).unwrap();
+        File::create(root_path.join("top.txt")).unwrap();
+
+        let roots = vec![
+            root_path.join("a"),
+            root_path.join("top.txt"),
+            root_path.join("missing"),
+        ];
+        let errors = ErrorReporter::new(true);
+        let (files, found) = mpsc::channel();
+        walk_parallel(&roots, &WalkOptions::default(), &errors, &files);
+        drop(files);
+
+        // The ignore file above the "a" root applies to it
+        assert_eq!(
+            sorted_relative(found.into_iter().collect(), root_path),
+            vec![PathBuf::from("a/b/c/deep.txt"), PathBuf::from("top.txt")]
+        );
+        let errors = errors.into_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind(), crate::error::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn walk_parallel_walks_nested_roots_on_their_own_and_sends_files_once() {
+        // File structure:
+        // root/
+        // |- .gitignore ("target/\n")
+        // |- .env
+        // |- src/
+        //  |- main.rs
+        // |- target/
+        //  |- a.txt
+        //  |- b.txt
+
+        // Create root folder
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path();
+
+        write(root_path.join(".gitignore"), "target/\n").unwrap();
+        File::create(root_path.join(".env")).unwrap();
+        create_dir(root_path.join("src")).unwrap();
+        File::create(root_path.join("src").join("main.rs")).unwrap();
+        create_dir(root_path.join("target")).unwrap();
+        File::create(root_path.join("target").join("a.txt")).unwrap();
+        File::create(root_path.join("target").join("b.txt")).unwrap();
+
+        // When passing "root", then roots it would skip or already covers
+        let roots = vec![
+            root_path.to_path_buf(),
+            root_path.join("target"),
+            root_path.join(".env"),
+            root_path.join("src").join("main.rs"),
+        ];
+        let errors = ErrorReporter::new(true);
+        let (files, found) = mpsc::channel();
+        walk_parallel(&roots, &WalkOptions::default(), &errors, &files);
+        drop(files);
+
+        assert_eq!(
+            sorted_relative(found.into_iter().collect(), root_path),
+            vec![
+                PathBuf::from(".env"),
+                PathBuf::from("src/main.rs"),
+                PathBuf::from("target/a.txt"),
+                PathBuf::from("target/b.txt"),
+            ]
+        );
+        assert!(errors.into_errors().is_empty());
+    }
+
+    #[test]
+    fn walk_parallel_applies_ignore_files_above_a_root_in_a_repository() {
+        // File structure:
+        // root/
+        // |- .git/
+        //  |- info/
+        //   |- exclude ("*.tmp\n")
+        // |- .gitignore ("*.log\n/a/b/gen/\n")
+        // |- a/
+        //  |- .gitignore ("!keep.log\n")
+        //  |- b/
+        //   |- deep.txt
+        //   |- deep.log
+        //   |- deep.tmp
+        //   |- keep.log
+        //   |- gen/
+        //    |- gen.txt
+
+        // Create root folder
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path();
+
+        write(root_path.join(".gitignore"), "*.log\n/a/b/gen/\n").unwrap();
+        let b_path = root_path.join("a").join("b");
+        create_dir_all(b_path.join("gen")).unwrap();
+        write(root_path.join("a").join(".gitignore"), "!keep.log\n").unwrap();
+        for name in ["deep.txt", "deep.log", "deep.tmp", "keep.log"] {
+            File::create(b_path.join(name)).unwrap();
+        }
+        File::create(b_path.join("gen").join("gen.txt")).unwrap();
+
+        // Outside a repository, ignore files above the root aren't read
+        let roots = vec![b_path.clone()];
+        let (files, found) = mpsc::channel();
+        walk_parallel(
+            &roots,
+            &WalkOptions::default(),
+            &ErrorReporter::new(true),
+            &files,
+        );
+        drop(files);
+        assert_eq!(found.into_iter().count(), 5);
+
+        let info_path = root_path.join(".git").join("info");
+        create_dir_all(&info_path).unwrap();
+        write(info_path.join("exclude"), "*.tmp\n").unwrap();
+
+        // When passing "root/a/b" as the only root
+        let errors = ErrorReporter::new(true);
+        let (files, found) = mpsc::channel();
+        walk_parallel(&roots, &WalkOptions::default(), &errors, &files);
+        drop(files);
+
+        assert_eq!(
+            sorted_relative(found.into_iter().collect(), &b_path),
+            vec![PathBuf::from("deep.txt"), PathBuf::from("keep.log")]
+        );
+        assert!(errors.into_errors().is_empty());
+    }
+
+    #[test]
+    fn collect_files_follows_symlinks_and_reports_loops() {
+        use std::os::unix::fs::symlink;
+
+        // File structure:
+        // root/
+        // |- real/
+        //  |- a.txt
+        //  |- back -> root
+        // |- linked -> real
+        // |- b.txt -> real/a.txt
+
+        // Create root folder
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path();
+
+        let real_path = root_path.join("real");
+        create_dir(&real_path).unwrap();
+        File::create(real_path.join("a.txt")).unwrap();
+        symlink(root_path, real_path.join("back")).unwrap();
+        symlink(&real_path, root_path.join("linked")).unwrap();
+        symlink(real_path.join("a.txt"), root_path.join("b.txt")).unwrap();
+
+        // Without --follow, links are skipped
+        let collected_paths = collect_files(
+            root_path,
+            &WalkOptions::default(),
+            &ErrorReporter::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            sorted_relative(collected_paths, root_path),
+            vec![PathBuf::from("real/a.txt")]
+        );
+
+        // When passing "root" with --follow
+        let options = WalkOptions {
+            follow: true,
+            ..WalkOptions::default()
+        };
+        let errors = ErrorReporter::new(true);
+        let collected_paths = collect_files(root_path, &options, &errors).unwrap();
+
+        assert_eq!(
+            sorted_relative(collected_paths, root_path),
+            vec![
+                PathBuf::from("b.txt"),
+                PathBuf::from("linked/a.txt"),
+                PathBuf::from("real/a.txt"),
+            ]
+        );
+        // "real/back" and "linked/back" both lead back to the root
+        let errors = errors.into_errors();
+        assert_eq!(errors.len(), 2);
+        for error in &errors {
+            assert_eq!(error.kind(), crate::error::ErrorKind::Loop);
+            assert!(matches!(error, Error::Loop { ancestor, .. } if ancestor == root_path));
+        }
+    }
+
+    #[test]
+    fn collect_files_applies_globs_relative_to_the_root_and_prunes_excluded_dirs() {
+        // File structure:
+        // root/
+        // |- src/
+        //  |- main.rs
+        //  |- notes.txt
+        //  |- generated/
+        //   |- gen.rs
+        //   |- back -> root
+
+        // Create root folder
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path();
+
+        let generated_path = root_path.join("src").join("generated");
+        create_dir_all(&generated_path).unwrap();
+        File::create(root_path.join("src").join("main.rs")).unwrap();
+        File::create(root_path.join("src").join("notes.txt")).unwrap();
+        File::create(generated_path.join("gen.rs")).unwrap();
+        std::os::unix::fs::symlink(root_path, generated_path.join("back")).unwrap();
+
+        // When passing "root" with -L -g '*.rs' -g '!src/generated/**'
+        let options = WalkOptions {
+            follow: true,
+            globs: Globs::new([("*.rs", false), ("!src/generated/**", false)]).unwrap(),
+            ..WalkOptions::default()
+        };
+        let errors = ErrorReporter::new(true);
+        let collected_paths = collect_files(root_path, &options, &errors).unwrap();
+
+        assert_eq!(
+            sorted_relative(collected_paths, root_path),
+            vec![PathBuf::from("src/main.rs")]
+        );
+        // The loop in the excluded directory is never found since it isn't read
+        assert!(errors.into_errors().is_empty());
+    }
+
+    #[test]
+    fn walk_parallel_follow_only_reports_broken_links_it_would_search() {
+        use std::os::unix::fs::symlink;
+
+        // File structure:
+        // root/
+        // |- .gitignore ("ignored\n")
+        // |- .hidden -> missing
+        // |- ignored -> missing
+        // |- excluded -> missing
+        // |- broken -> missing
+
+        // Create root folder
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path();
+
+        write(root_path.join(".gitignore"), "ignored\n").unwrap();
+        for name in [".hidden", "ignored", "excluded", "broken"] {
+            symlink(root_path.join("missing"), root_path.join(name)).unwrap();
+        }
+
+        // When passing "root" with -L -g '!excluded'
+        let options = WalkOptions {
+            follow: true,
+            globs: Globs::new([("!excluded", false)]).unwrap(),
+            ..WalkOptions::default()
+        };
+        let roots = vec![root_path.to_path_buf()];
+        let errors = ErrorReporter::new(true);
+        let (files, found) = mpsc::channel();
+        walk_parallel(&roots, &options, &errors, &files);
+        drop(files);
+
+        assert_eq!(found.into_iter().count(), 0);
+        let errors = errors.into_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path(), Some(root_path.join("broken").as_path()));
+        assert_eq!(errors[0].kind(), crate::error::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn dedup_roots_drops_only_repeated_roots() {
+        // File structure:
+        // root/
+        // |- src/
+        //  |- main.rs
+        // |- tests/
+
+        // Create root folder
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path();
+
+        let src_path = root_path.join("src");
+        create_dir(&src_path).unwrap();
+        File::create(src_path.join("main.rs")).unwrap();
+        let tests_path = root_path.join("tests");
+        create_dir(&tests_path).unwrap();
+
+        let roots = vec![
+            src_path.join("main.rs"),
+            src_path.clone(),
+            tests_path.clone(),
+            src_path.join(".").join("..").join("src"),
+        ];
+
+        assert_eq!(
+            dedup_roots(&roots),
+            vec![src_path.join("main.rs"), src_path, tests_path]
+        );
+    }
+
+    #[test]
+    fn dedup_roots_keeps_unresolvable_roots() {
+        let roots = vec![
+            PathBuf::from("does/not/exist"),
+            PathBuf::from("does/not/exist"),
+        ];
+
+        assert_eq!(dedup_roots(&roots), vec![PathBuf::from("does/not/exist")]);
+    }
+
+    #[test]
+    fn collect_files_missing_root_returns_error() {
+        // Create root folder
+        let root = tempfile::tempdir().unwrap();
+        let missing_path = root.path().join("missing");
+
+        let result = collect_files(
+            &missing_path,
+            &WalkOptions::default(),
+            &ErrorReporter::default(),
+        );
+
+        assert_eq!(
+            result.unwrap_err().kind(),
+            crate::error::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn sort_files_by_path_and_modified_time() {
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path();
+
+        let older = root_path.join("b.txt");
+        let newer = root_path.join("a.txt");
+        write(&older, "").unwrap();
+        write(&newer, "").unwrap();
+        let modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        File::options()
+            .write(true)
+            .open(&older)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let mut files = vec![older.clone(), newer.clone()];
+        sort_files(&mut files, SortBy::Path);
+        assert_eq!(files, vec![newer.clone(), older.clone()]);
+
+        sort_files(&mut files, SortBy::Modified);
+        assert_eq!(files, vec![older.clone(), newer.clone()]);
+
+        sort_files(&mut files, SortBy::None);
+        assert_eq!(files, vec![older, newer]);
+    }
 }
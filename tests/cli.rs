@@ -20,7 +20,7 @@ fn cli_returns_0_and_prints_match_when_found() {
     file_with_pattern.flush().unwrap();
     drop(file_with_pattern);
 
-    let mut cmd = Command::cargo_bin("rgrep").unwrap();
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
     let assert = cmd.arg("Hello").arg(root_path).assert();
 
     assert
@@ -59,7 +59,7 @@ fn cli_prints_matches_even_if_error_occurs_and_exits_2() {
     permissions.set_mode(permissions.mode() & !0o444);
     set_permissions(file_no_permissions_path, permissions).unwrap();
 
-    let mut cmd = Command::cargo_bin("rgrep").unwrap();
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
     let assert = cmd.arg("Hello").arg(root_path).assert();
 
     assert
@@ -70,3 +70,947 @@ fn cli_prints_matches_even_if_error_occurs_and_exits_2() {
         }))
         .stderr(predicate::function(|x: &str| !x.is_empty()));
 }
+
+#[test]
+fn cli_skips_gitignored_files_unless_no_ignore() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    // Ignore every log file
+    std::fs::write(root_path.join(".gitignore"), "*.log\n").unwrap();
+
+    // Create an ignored file containing 'Hello' pattern in it
+    std::fs::write(root_path.join("debug.log"), "Hello World!\n").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    cmd.arg("Hello")
+        .arg(root_path)
+        .assert()
+        .code(predicate::eq(1));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd.arg("--no-ignore").arg("Hello").arg(root_path).assert();
+
+    assert
+        .success()
+        .stdout(predicate::str::contains("debug.log:1:Hello World!"));
+}
+
+#[test]
+fn cli_unrestricted_ladder_widens_the_search() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    std::fs::write(root_path.join(".gitignore"), "*.log\n").unwrap();
+    std::fs::write(root_path.join("debug.log"), "Hello log\n").unwrap();
+    std::fs::write(root_path.join(".env"), "Hello env\n").unwrap();
+    std::fs::create_dir(root_path.join(".git")).unwrap();
+    std::fs::write(root_path.join(".git").join("HEAD"), "Hello git\n").unwrap();
+    std::fs::write(root_path.join("data.bin"), "Hello\0bin\n").unwrap();
+
+    let search = |flags: &[&str]| {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+        let output = cmd
+            .args(flags)
+            .arg("-l")
+            .arg("Hello")
+            .arg(root_path)
+            .output();
+        let stdout = String::from_utf8(output.unwrap().stdout).unwrap();
+        let mut names: Vec<String> = stdout
+            .lines()
+            .map(|line| line.strip_prefix(root_path.to_str().unwrap()).unwrap()[1..].to_string())
+            .collect();
+        names.sort();
+        names
+    };
+
+    assert!(search(&[]).is_empty());
+    assert_eq!(search(&["--hidden"]), vec![".env"]);
+    assert_eq!(search(&["-u"]), vec!["debug.log"]);
+    assert_eq!(search(&["-uu"]), vec![".env", ".git/HEAD", "debug.log"]);
+    assert_eq!(
+        search(&["-uuu"]),
+        vec![".env", ".git/HEAD", "data.bin", "debug.log"]
+    );
+}
+
+#[test]
+fn cli_follow_searches_linked_directories_and_reports_loops() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    let real_path = root_path.join("real");
+    std::fs::create_dir(&real_path).unwrap();
+    std::fs::write(real_path.join("a.txt"), "Hello\n").unwrap();
+    std::os::unix::fs::symlink(root_path, real_path.join("back")).unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .arg("-L")
+        .arg("--no-heading")
+        .arg("Hello")
+        .arg(&real_path)
+        .assert();
+
+    // real/back/real is real itself, so its files are only searched once
+    let loop_path = real_path.join("back").join("real");
+    assert
+        .code(predicate::eq(2))
+        .stdout(format!("{}:1:Hello\n", real_path.join("a.txt").display()))
+        .stderr(format!(
+            "rgrep: {}: file system loop found, it points to ancestor {}\n",
+            loop_path.display(),
+            real_path.display()
+        ));
+}
+
+#[test]
+fn cli_globs_include_and_exclude_files() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    let generated_path = root_path.join("src").join("generated");
+    std::fs::create_dir_all(&generated_path).unwrap();
+    std::fs::write(root_path.join("src").join("main.rs"), "Hello\n").unwrap();
+    std::fs::write(root_path.join("src").join("LIB.RS"), "Hello\n").unwrap();
+    std::fs::write(generated_path.join("gen.rs"), "Hello\n").unwrap();
+    std::fs::write(root_path.join("notes.txt"), "Hello\n").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .arg("-l")
+        .arg("--sort=path")
+        .arg("-g")
+        .arg("*.rs")
+        .arg("-g")
+        .arg("!**/generated/**")
+        .arg("Hello")
+        .arg(root_path)
+        .assert();
+
+    assert
+        .success()
+        .stdout(format!("{}\n", root_path.join("src/main.rs").display()));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .arg("-l")
+        .arg("--sort=path")
+        .arg("--iglob")
+        .arg("*.rs")
+        .arg("Hello")
+        .arg(root_path)
+        .assert();
+
+    assert.success().stdout(format!(
+        "{}\n{}\n{}\n",
+        root_path.join("src/LIB.RS").display(),
+        root_path.join("src/generated/gen.rs").display(),
+        root_path.join("src/main.rs").display()
+    ));
+}
+
+#[test]
+fn cli_stops_quietly_when_the_output_pipe_closes() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    for index in 0..50 {
+        std::fs::write(root_path.join(format!("{index}.txt")), "Hello\n").unwrap();
+    }
+
+    // The reading end of stdout is closed before anything is written, like `rgrep | head -0`
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"))
+        .arg("Hello")
+        .arg(root_path)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    drop(child.stdout.take());
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn cli_searches_nested_roots_that_the_enclosing_root_skips() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    std::fs::create_dir(root_path.join(".git")).unwrap();
+    std::fs::write(root_path.join(".gitignore"), "target/\n").unwrap();
+    std::fs::create_dir(root_path.join("target")).unwrap();
+    std::fs::write(root_path.join("target").join("a.txt"), "needle\n").unwrap();
+    std::fs::create_dir(root_path.join("src")).unwrap();
+    std::fs::write(root_path.join("src").join("b.txt"), "needle\n").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .current_dir(root_path)
+        .args(["-l", "--sort=path", "needle", ".", "target/a.txt"])
+        .assert();
+    assert.success().stdout("./src/b.txt\ntarget/a.txt\n");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .current_dir(root_path)
+        .args(["-l", "--sort=path", "needle", ".", "target"])
+        .assert();
+    assert.success().stdout("./src/b.txt\ntarget/a.txt\n");
+
+    // Globs don't apply to explicit roots, and files are printed once
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .current_dir(root_path)
+        .args(["-l", "-g", "*.md", "needle", ".", "src/b.txt"])
+        .assert();
+    assert.success().stdout("src/b.txt\n");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .current_dir(root_path)
+        .args(["-l", "needle", ".", "src/b.txt"])
+        .assert();
+    assert.success().stdout("src/b.txt\n");
+}
+
+#[test]
+fn cli_prints_context_lines_with_group_separators() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    // Two matches close enough to share context, and a third one further away
+    let file_path = root_path.join("context.txt");
+    std::fs::write(
+        &file_path,
+        "a\nHello 1\nb\nHello 2\nc\nd\ne\nf\nHello 3\ng\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd.arg("-C").arg("1").arg("Hello").arg(&file_path).assert();
+
+    let path = file_path.display();
+    let expected = format!(
+        "{path}-1-a\n{path}:2:Hello 1\n{path}-3-b\n{path}:4:Hello 2\n{path}-5-c\n--\n{path}-8-f\n{path}:9:Hello 3\n{path}-10-g\n"
+    );
+    assert.success().stdout(predicate::eq(expected));
+}
+
+#[test]
+fn cli_prints_no_group_separators_without_context() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    let file_path = root_path.join("context.txt");
+    std::fs::write(&file_path, "Hello 1\nother\nHello 2\n").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd.arg("Hello").arg(&file_path).assert();
+
+    let path = file_path.display();
+    let expected = format!("{path}:1:Hello 1\n{path}:3:Hello 2\n");
+    assert.success().stdout(predicate::eq(expected));
+}
+
+#[test]
+fn cli_after_context_overrides_context() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    let file_path = root_path.join("context.txt");
+    std::fs::write(&file_path, "a\nb\nHello\nc\nd\n").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .arg("-A")
+        .arg("2")
+        .arg("-C")
+        .arg("1")
+        .arg("Hello")
+        .arg(&file_path)
+        .assert();
+
+    let path = file_path.display();
+    let expected = format!("{path}-2-b\n{path}:3:Hello\n{path}-4-c\n{path}-5-d\n");
+    assert.success().stdout(predicate::eq(expected));
+}
+
+#[test]
+fn cli_ignore_case_matches_literals_and_regexes() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    let file_path = root_path.join("case.txt");
+    std::fs::write(&file_path, "HELLO World\nStraße\nnothing\n").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd.arg("-i").arg("hello").arg(&file_path).assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains(":1:HELLO World"));
+
+    // Unicode case folding for literals
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd.arg("-i").arg("STRAẞE").arg(&file_path).assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains(":2:Straße"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .arg("--regex")
+        .arg("--ignore-case")
+        .arg("^hel+o")
+        .arg(&file_path)
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains(":1:HELLO World"));
+}
+
+#[test]
+fn cli_smart_case_is_sensitive_only_with_uppercase_pattern() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    let file_path = root_path.join("case.txt");
+    std::fs::write(&file_path, "HELLO World\n").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    cmd.arg("-S").arg("hello").arg(&file_path).assert().code(0);
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    cmd.arg("-S").arg("Hello").arg(&file_path).assert().code(1);
+}
+
+#[test]
+fn cli_sort_path_prints_files_in_order() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    let mut expected = String::new();
+    for name in ["a", "b", "c", "d", "e", "f", "g", "h"] {
+        let file_path = root_path.join(format!("{name}.txt"));
+        std::fs::write(&file_path, "Hello\nskip\nHello again\n").unwrap();
+        expected.push_str(&format!(
+            "{0}:1:Hello\n{0}:3:Hello again\n",
+            file_path.display()
+        ));
+    }
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd.arg("--sort=path").arg("Hello").arg(root_path).assert();
+
+    assert.success().stdout(predicate::eq(expected));
+}
+
+#[test]
+fn cli_heading_groups_matches_under_the_path() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    let first = root_path.join("a.txt");
+    let second = root_path.join("b.txt");
+    std::fs::write(&first, "Hello\nskip\nHello again\n").unwrap();
+    std::fs::write(&second, "skip\nHello\n").unwrap();
+    std::fs::write(root_path.join("c.txt"), "skip\n").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .arg("--heading")
+        .arg("--sort=path")
+        .arg("Hello")
+        .arg(root_path)
+        .assert();
+
+    assert.success().stdout(predicate::eq(format!(
+        "{}\n1:Hello\n3:Hello again\n\n{}\n2:Hello\n",
+        first.display(),
+        second.display()
+    )));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .arg("--heading")
+        .arg("--no-heading")
+        .arg("Hello")
+        .arg(&second)
+        .assert();
+
+    assert
+        .success()
+        .stdout(predicate::eq(format!("{}:2:Hello\n", second.display())));
+}
+
+#[test]
+fn cli_json_prints_one_event_per_line() {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("rgrep");
+    let assert = cmd
+        .arg("--json")
+        .arg("-A1")
+        .arg("cat")
+        .write_stdin("a cat\ndog\n")
+        .assert();
+
+    let path = r#""path":{"text":"(standard input)"}"#;
+    let stats = r#""stats":{"searches":1,"searches_with_match":1,"bytes_searched":10,"matched_lines":1,"matches":1}"#;
+    assert.success().stdout(predicate::eq(format!(
+        concat!(
+            "{{\"type\":\"begin\",\"data\":{{{path}}}}}\n",
+            "{{\"type\":\"match\",\"data\":{{{path},\"lines\":{{\"text\":\"a cat\"}},\"line_number\":1,\"absolute_offset\":0,\"submatches\":[{{\"match\":{{\"text\":\"cat\"}},\"start\":2,\"end\":5}}]}}}}\n",
+            "{{\"type\":\"context\",\"data\":{{{path},\"lines\":{{\"text\":\"dog\"}},\"line_number\":2,\"absolute_offset\":6,\"submatches\":[]}}}}\n",
+            "{{\"type\":\"end\",\"data\":{{{path},{stats}}}}}\n",
+            "{{\"type\":\"summary\",\"data\":{{{stats}}}}}\n",
+        ),
+        path = path,
+        stats = stats
+    )));
+}
+
+#[test]
+fn cli_json_encodes_non_utf8_paths_as_base64() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    let file_path = root_path.join(OsStr::from_bytes(b"caf\xe9.txt"));
+    std::fs::write(&file_path, "Hello\n").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd.arg("--json").arg("Hello").arg(root_path).assert();
+
+    assert
+        .success()
+        .stdout(predicate::str::contains(
+            r#"{"type":"begin","data":{"path":{"bytes":""#,
+        ))
+        .stdout(predicate::str::contains(r#""lines":{"text":"Hello"}"#));
+}
+
+#[test]
+fn cli_searches_lines_that_are_not_valid_utf8() {
+    // Latin-1 text, with a stray byte past the sniffed prefix as well
+    let mut input = b"caf\xe9 ol\xe9\n".to_vec();
+    input.extend_from_slice(&[b'a'; 5000]);
+    input.extend_from_slice(b"\n\xff ol\xe9\n");
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("rgrep");
+    let assert = cmd.arg("ol").write_stdin(input).assert();
+
+    assert.success().stdout(predicate::eq(
+        "(standard input):1:caf\u{fffd} ol\u{fffd}\n(standard input):3:\u{fffd} ol\u{fffd}\n",
+    ));
+}
+
+#[test]
+fn cli_mmap_and_no_mmap_print_the_same_matches() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let file_path = root.path().join("a.txt");
+    std::fs::write(&file_path, "one\nHello\nthree\nHello again").unwrap();
+
+    for flag in ["--mmap", "--no-mmap"] {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+        let assert = cmd
+            .arg(flag)
+            .arg("--no-heading")
+            .arg("Hello")
+            .arg(&file_path)
+            .assert();
+
+        let path = file_path.display();
+        assert
+            .success()
+            .stdout(format!("{path}:2:Hello\n{path}:4:Hello again\n"));
+    }
+}
+
+#[test]
+fn cli_prints_each_file_matches_contiguously() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    let contents = "Hello\n".repeat(500);
+    for index in 0..16 {
+        std::fs::write(root_path.join(format!("{index}.txt")), &contents).unwrap();
+    }
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let output = cmd.arg("Hello").arg(root_path).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    let mut paths: Vec<&str> = stdout
+        .lines()
+        .map(|line| line.split_once(':').unwrap().0)
+        .collect();
+    assert_eq!(paths.len(), 16 * 500);
+    paths.dedup();
+    assert_eq!(paths.len(), 16);
+}
+
+#[test]
+fn cli_no_messages_suppresses_errors_but_still_exits_2() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    let file_path = root_path.join("a.txt");
+    std::fs::write(&file_path, "Hello\n").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .arg("--no-messages")
+        .arg("Hello")
+        .arg(&file_path)
+        .arg(root_path.join("missing"))
+        .assert();
+
+    assert
+        .code(2)
+        .stdout(predicate::str::contains("a.txt:1:Hello"))
+        .stderr(predicate::str::is_empty());
+}
+
+#[test]
+fn cli_error_summary_counts_errors_per_category() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .arg("-s")
+        .arg("--error-summary")
+        .arg("Hello")
+        .arg(root_path.join("missing"))
+        .arg(root_path.join("gone"))
+        .assert();
+
+    assert
+        .code(2)
+        .stderr(predicate::eq("rgrep: 2 errors: 2 not found\n"));
+}
+
+#[test]
+fn cli_help_prints_usage_and_exits_0() {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd.arg("--help").assert();
+
+    assert
+        .success()
+        .stdout(predicate::str::contains("Usage: rgrep"))
+        .stdout(predicate::str::contains("--ignore-case"));
+}
+
+#[test]
+fn cli_version_prints_version_and_exits_0() {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd.arg("--version").assert();
+
+    assert
+        .success()
+        .stdout(predicate::str::starts_with("rgrep "));
+}
+
+#[test]
+fn cli_usage_error_exits_2() {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd.arg("--bogus").arg("Hello").arg(".").assert();
+
+    assert
+        .failure()
+        .code(predicate::eq(2))
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("unrecognized option '--bogus'"))
+        .stderr(predicate::str::contains("Usage: rgrep"));
+}
+
+#[test]
+fn cli_accepts_clustered_flags_and_pattern_option() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    let file_path = root_path.join("dash.txt");
+    std::fs::write(&file_path, "x\n--FLAG here\ny\n").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .arg("-iA1")
+        .arg("-e")
+        .arg("--flag")
+        .arg(&file_path)
+        .assert();
+
+    let path = file_path.display();
+    assert
+        .success()
+        .stdout(predicate::eq(format!("{path}:2:--FLAG here\n{path}-3-y\n")));
+}
+
+#[test]
+fn cli_searches_multiple_roots_once() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    let src_path = root_path.join("src");
+    std::fs::create_dir(&src_path).unwrap();
+    std::fs::write(src_path.join("lib.rs"), "Hello src\n").unwrap();
+    std::fs::write(root_path.join("build.rs"), "Hello build\n").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .arg("Hello")
+        .arg(&src_path)
+        .arg(root_path.join("build.rs"))
+        .arg(src_path.join("lib.rs"))
+        .assert();
+
+    assert.success().stdout(predicate::function(|x: &str| {
+        x.matches("lib.rs:1:Hello src").count() == 1
+            && x.matches("build.rs:1:Hello build").count() == 1
+    }));
+}
+
+#[test]
+fn cli_defaults_to_current_directory() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    std::fs::write(root_path.join("a.txt"), "Hello World!\n").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd.current_dir(root_path).arg("Hello").assert();
+
+    assert
+        .success()
+        .stdout(predicate::eq("./a.txt:1:Hello World!\n"));
+}
+
+#[test]
+fn cli_missing_path_is_reported_and_exits_2() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    std::fs::write(root_path.join("a.txt"), "Hello World!\n").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .arg("Hello")
+        .arg(root_path.join("missing"))
+        .arg(root_path.join("a.txt"))
+        .assert();
+
+    assert
+        .code(predicate::eq(2))
+        .stdout(predicate::str::contains("a.txt:1:Hello World!"))
+        .stderr(predicate::str::contains("missing"));
+}
+
+#[test]
+fn cli_searches_stdin_for_dash_path() {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("rgrep");
+    let assert = cmd
+        .arg("Hello")
+        .arg("-")
+        .write_stdin("first\nHello stdin\n")
+        .assert();
+
+    assert
+        .success()
+        .stdout(predicate::eq("(standard input):2:Hello stdin\n"));
+}
+
+#[test]
+fn cli_searches_piped_stdin_without_path_using_label() {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("rgrep");
+    let assert = cmd
+        .arg("--label")
+        .arg("piped")
+        .arg("-C1")
+        .arg("Hello")
+        .write_stdin("first\nHello stdin\n")
+        .assert();
+
+    assert
+        .success()
+        .stdout(predicate::eq("piped-1-first\npiped:2:Hello stdin\n"));
+}
+
+#[test]
+fn cli_searches_stdin_alongside_files() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    std::fs::write(root_path.join("a.txt"), "Hello file\n").unwrap();
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("rgrep");
+    let assert = cmd
+        .arg("Hello")
+        .arg(root_path)
+        .arg("-")
+        .write_stdin("Hello stdin\n")
+        .assert();
+
+    assert
+        .success()
+        .stdout(predicate::str::contains("a.txt:1:Hello file"))
+        .stdout(predicate::str::contains("(standard input):1:Hello stdin"));
+}
+
+#[test]
+fn cli_invert_match_prints_non_matching_lines() {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("rgrep");
+    let assert = cmd
+        .arg("-v")
+        .arg("Hello")
+        .write_stdin("Hello\nWorld\nHello again\n")
+        .assert();
+
+    assert
+        .success()
+        .stdout(predicate::eq("(standard input):2:World\n"));
+
+    // Every line matches, so nothing is selected
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("rgrep");
+    let assert = cmd
+        .arg("-v")
+        .arg("Hello")
+        .write_stdin("Hello\nHello again\n")
+        .assert();
+
+    assert
+        .code(predicate::eq(1))
+        .stdout(predicate::str::is_empty());
+}
+
+#[test]
+fn cli_word_and_line_regexp() {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("rgrep");
+    let assert = cmd
+        .arg("-w")
+        .arg("cat")
+        .write_stdin("concatenate\nthe cat sat\ncat\n")
+        .assert();
+
+    assert.success().stdout(predicate::eq(
+        "(standard input):2:the cat sat\n(standard input):3:cat\n",
+    ));
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("rgrep");
+    let assert = cmd
+        .arg("-x")
+        .arg("--regex")
+        .arg("c.t")
+        .write_stdin("concatenate\nthe cat sat\ncat\n")
+        .assert();
+
+    assert
+        .success()
+        .stdout(predicate::eq("(standard input):3:cat\n"));
+}
+
+#[test]
+fn cli_matches_any_of_multiple_patterns() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    let pattern_file = root_path.join("deprecated.txt");
+    std::fs::write(&pattern_file, "old_api\nlegacy_call\n").unwrap();
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("rgrep");
+    let assert = cmd
+        .arg("-f")
+        .arg(&pattern_file)
+        .arg("-e")
+        .arg("TODO")
+        .write_stdin("old_api()\nnew_api()\nlegacy_call()\n// TODO\n")
+        .assert();
+
+    assert.success().stdout(predicate::eq(
+        "(standard input):1:old_api()\n(standard input):3:legacy_call()\n(standard input):4:// TODO\n",
+    ));
+}
+
+#[test]
+fn cli_count_modes_print_per_file_totals() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    let file_path = root_path.join("api.rs");
+    std::fs::write(&file_path, "old_api(old_api)\nnew_api\nold_api\n").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd.arg("-c").arg("old_api").arg(&file_path).assert();
+    assert
+        .success()
+        .stdout(predicate::eq(format!("{}:2\n", file_path.display())));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .arg("--count-matches")
+        .arg("old_api")
+        .arg(&file_path)
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::eq(format!("{}:3\n", file_path.display())));
+
+    // Files without matches aren't listed
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd.arg("-c").arg("missing").arg(&file_path).assert();
+    assert
+        .code(predicate::eq(1))
+        .stdout(predicate::str::is_empty());
+}
+
+#[test]
+fn cli_files_with_and_without_matches() {
+    // Create root folder
+    let root = tempdir().unwrap();
+    let root_path = root.path();
+
+    let with_path = root_path.join("with.txt");
+    std::fs::write(&with_path, "Hello\nHello again\n").unwrap();
+    let without_path = root_path.join("without.txt");
+    std::fs::write(&without_path, "Goodbye\n").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd.arg("-l").arg("Hello").arg(root_path).assert();
+    assert
+        .success()
+        .stdout(predicate::eq(format!("{}\n", with_path.display())));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .arg("--files-without-match")
+        .arg("Hello")
+        .arg(root_path)
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::eq(format!("{}\n", without_path.display())));
+
+    // Every file matches, so none is listed
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("rgrep"));
+    let assert = cmd
+        .arg("--files-without-match")
+        .arg("Hello")
+        .arg(&with_path)
+        .assert();
+    assert
+        .code(predicate::eq(1))
+        .stdout(predicate::str::is_empty());
+}
+
+#[test]
+fn cli_only_matching_prints_each_match() {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("rgrep");
+    let assert = cmd
+        .arg("-o")
+        .arg("--regex")
+        .arg("ab+")
+        .write_stdin("xab abbb\nnone\nabb\n")
+        .assert();
+
+    assert.success().stdout(predicate::eq(
+        "(standard input):1:ab\n(standard input):1:abbb\n(standard input):3:abb\n",
+    ));
+}
+
+#[test]
+fn cli_column_prints_first_match_column() {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("rgrep");
+    let assert = cmd
+        .arg("--column")
+        .arg("-A1")
+        .arg("cat")
+        .write_stdin("the cat and cat\nafter\n")
+        .assert();
+
+    assert.success().stdout(predicate::eq(
+        "(standard input):1:5:the cat and cat\n(standard input)-2-after\n",
+    ));
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("rgrep");
+    let assert = cmd
+        .arg("-o")
+        .arg("--column")
+        .arg("cat")
+        .write_stdin("the cat and cat\n")
+        .assert();
+
+    assert.success().stdout(predicate::eq(
+        "(standard input):1:5:cat\n(standard input):1:13:cat\n",
+    ));
+}
+
+#[test]
+fn cli_color_always_highlights_output() {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("rgrep");
+    let assert = cmd
+        .arg("--color=always")
+        .arg("--label=in")
+        .arg("cat")
+        .write_stdin("a cat\n")
+        .assert();
+
+    assert.success().stdout(predicate::eq(
+        "\x1b[35min\x1b[0m:\x1b[32m1\x1b[0m:a \x1b[1;31mcat\x1b[0m\n",
+    ));
+}
+
+#[test]
+fn cli_colors_spec_changes_match_color() {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("rgrep");
+    let assert = cmd
+        .arg("--color=always")
+        .arg("--colors=match:none")
+        .arg("--colors=match:fg:blue")
+        .arg("--colors=path:none")
+        .arg("--colors=line:none")
+        .arg("cat")
+        .write_stdin("a cat\n")
+        .assert();
+
+    assert
+        .success()
+        .stdout(predicate::eq("(standard input):1:a \x1b[34mcat\x1b[0m\n"));
+}
+
+#[test]
+fn cli_color_auto_is_plain_when_not_a_terminal() {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("rgrep");
+    let assert = cmd.arg("cat").write_stdin("a cat\n").assert();
+
+    assert
+        .success()
+        .stdout(predicate::eq("(standard input):1:a cat\n"));
+}